@@ -0,0 +1,518 @@
+//! Threshold-based alert rules evaluated against weather, with notifications on state changes.
+
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uom::si::f64::{Pressure, Ratio, ThermodynamicTemperature, Velocity};
+use uom::si::pressure::{hectopascal, millibar, pascal};
+use uom::si::ratio::percent;
+use uom::si::thermodynamic_temperature::{degree_celsius, degree_fahrenheit, kelvin};
+use uom::si::velocity::{kilometer_per_hour, meter_per_second, mile_per_hour};
+use url::Url;
+
+use crate::api::Client;
+use crate::data::{Condition, Location, Provider, Weather};
+use crate::error::{Error, Result};
+use crate::storage;
+
+/// Named rule along with the ways to notify when it is triggered or resolved.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct Alert {
+    pub name: String,
+    pub rule: Rule,
+    /// Shell command, weather fields are passed in `WEATHER_*` environment variables.
+    #[serde(default)]
+    pub command: Option<String>,
+    /// URL to POST the event as JSON to.
+    #[serde(default)]
+    pub webhook: Option<Url>,
+    /// File to append the event to as a JSON line.
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+}
+
+/// Rule like `temp < -5 at @datacenter`, `wind > 15 m/s` or `condition in [thunderstorm, hail]`.
+///
+/// Without `at @<name>` the rule applies to the saved location, otherwise to the known one.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rule {
+    text: String,
+    predicate: Predicate,
+    location: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Predicate {
+    /// Value of the field in SI base units, e.g. kelvins or pascals.
+    Compare(Field, Op, f64),
+    ConditionIn(Vec<Condition>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Field {
+    Temperature,
+    Humidity,
+    Pressure,
+    WindSpeed,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Rule {
+    /// Name of the known location the rule applies to, the saved one if `None`.
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    /// Whether the weather triggers the rule, values not reported by the provider never do.
+    pub fn matches(&self, weather: &Weather) -> bool {
+        match &self.predicate {
+            Predicate::Compare(field, op, threshold) => {
+                let value = match field {
+                    Field::Temperature => Some(weather.temperature.value),
+                    Field::Humidity => weather.humidity.map(|h| h.value),
+                    Field::Pressure => weather.pressure.map(|p| p.value),
+                    Field::WindSpeed => weather.wind_speed.map(|v| v.value),
+                };
+                value.is_some_and(|value| match op {
+                    Op::Lt => value < *threshold,
+                    Op::Le => value <= *threshold,
+                    Op::Gt => value > *threshold,
+                    Op::Ge => value >= *threshold,
+                })
+            }
+            Predicate::ConditionIn(conditions) => conditions.contains(&weather.condition),
+        }
+    }
+}
+
+impl TryFrom<String> for Rule {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let (predicate, location) = match text.rsplit_once(" at ") {
+            Some((predicate, location)) => {
+                let name = location
+                    .trim()
+                    .strip_prefix('@')
+                    .filter(|name| !name.is_empty())
+                    .ok_or_else(|| {
+                        format!("location should be given as \"@<name>\" in \"{text}\"")
+                    })?;
+                (predicate, Some(name.to_string()))
+            }
+            None => (text.as_str(), None),
+        };
+        Ok(Self {
+            predicate: parse_predicate(predicate.trim())
+                .map_err(|reason| format!("rule \"{text}\" is invalid: {reason}"))?,
+            location,
+            text,
+        })
+    }
+}
+
+impl From<Rule> for String {
+    fn from(rule: Rule) -> Self {
+        rule.text
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+fn parse_predicate(text: &str) -> Result<Predicate, String> {
+    let mut parts = text.splitn(3, char::is_whitespace);
+    let (Some(field), Some(op), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err("expected \"<field> <operator> <value>\"".to_string());
+    };
+
+    if field == "condition" {
+        if op != "in" {
+            return Err("conditions are matched with \"in [...]\"".to_string());
+        }
+        let list = value
+            .trim()
+            .strip_prefix('[')
+            .and_then(|v| v.strip_suffix(']'))
+            .ok_or("conditions should be listed in brackets")?;
+        let conditions = list
+            .split(',')
+            .map(|name| parse_condition(name.trim()))
+            .collect::<Result<_, _>>()?;
+        return Ok(Predicate::ConditionIn(conditions));
+    }
+
+    let op = match op {
+        "<" => Op::Lt,
+        "<=" => Op::Le,
+        ">" => Op::Gt,
+        ">=" => Op::Ge,
+        _ => return Err(format!("unknown operator \"{op}\", use <, <=, > or >=")),
+    };
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || "+-.".contains(c)))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("\"{value}\" is not a number"))?;
+    let unknown_unit = || format!("unknown unit \"{}\" of {field}", unit.trim());
+    let (field, value) = match field {
+        "temp" | "temperature" => {
+            let value = match unit.trim() {
+                "" | "C" | "°C" => ThermodynamicTemperature::new::<degree_celsius>(number),
+                "F" | "°F" => ThermodynamicTemperature::new::<degree_fahrenheit>(number),
+                "K" => ThermodynamicTemperature::new::<kelvin>(number),
+                _ => return Err(unknown_unit()),
+            };
+            (Field::Temperature, value.value)
+        }
+        "humidity" => {
+            let value = match unit.trim() {
+                "" | "%" => Ratio::new::<percent>(number),
+                _ => return Err(unknown_unit()),
+            };
+            (Field::Humidity, value.value)
+        }
+        "pressure" => {
+            let value = match unit.trim() {
+                "" | "hPa" => Pressure::new::<hectopascal>(number),
+                "mb" | "mbar" => Pressure::new::<millibar>(number),
+                "Pa" => Pressure::new::<pascal>(number),
+                _ => return Err(unknown_unit()),
+            };
+            (Field::Pressure, value.value)
+        }
+        "wind" => {
+            let value = match unit.trim() {
+                "" | "m/s" => Velocity::new::<meter_per_second>(number),
+                "km/h" => Velocity::new::<kilometer_per_hour>(number),
+                "mph" => Velocity::new::<mile_per_hour>(number),
+                _ => return Err(unknown_unit()),
+            };
+            (Field::WindSpeed, value.value)
+        }
+        _ => {
+            return Err(format!(
+                "unknown field \"{field}\", use temp, humidity, pressure, wind or condition"
+            ))
+        }
+    };
+    Ok(Predicate::Compare(field, op, value))
+}
+
+/// Parse condition written in any case, with words separated by `_`, `-` or nothing.
+fn parse_condition(name: &str) -> Result<Condition, String> {
+    let pascal_case: String = name
+        .split(['_', '-', ' '])
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect();
+    serde_json::from_value(Value::String(pascal_case))
+        .map_err(|_| format!("unknown condition \"{name}\""))
+}
+
+/// Names of triggered alerts, persisted between runs so every change is notified only once.
+#[derive(Deserialize, Serialize, Default, Debug)]
+pub struct State {
+    #[serde(default)]
+    triggered: BTreeSet<String>,
+    /// Triggered alerts shown on the desktop by the daemon, kept apart from other notifications,
+    /// so a failing webhook does not show the same change again on every fetch.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    shown: BTreeSet<String>,
+}
+
+impl State {
+    pub fn load(path: &Path) -> Result<Self> {
+        storage::read_file(path)
+    }
+
+    /// Replace the file atomically under the lock, as `check` and the daemon can store it at once.
+    pub fn store(&self, path: &Path) -> Result<()> {
+        let _lock = storage::lock(path)?;
+        storage::write_file(path, self)
+    }
+
+    pub fn is_triggered(&self, alert: &str) -> bool {
+        self.triggered.contains(alert)
+    }
+
+    /// Remember the state, return whether it has changed.
+    pub fn update(&mut self, alert: &str, triggered: bool) -> bool {
+        set(&mut self.triggered, alert, triggered)
+    }
+
+    pub fn is_shown(&self, alert: &str) -> bool {
+        self.shown.contains(alert)
+    }
+
+    /// Remember the state shown on the desktop, return whether it has changed.
+    pub fn update_shown(&mut self, alert: &str, triggered: bool) -> bool {
+        set(&mut self.shown, alert, triggered)
+    }
+
+    /// Forget alerts that are not in the config anymore.
+    pub fn retain(&mut self, alerts: &[Alert]) {
+        let is_defined = |name: &String| alerts.iter().any(|alert| &alert.name == name);
+        self.triggered.retain(is_defined);
+        self.shown.retain(is_defined);
+    }
+}
+
+fn set(names: &mut BTreeSet<String>, alert: &str, triggered: bool) -> bool {
+    if triggered {
+        names.insert(alert.to_string())
+    } else {
+        names.remove(alert)
+    }
+}
+
+/// Change of the alert state as sent to notifications.
+#[derive(Serialize, Clone, Debug)]
+pub struct Event {
+    pub alert: String,
+    pub rule: String,
+    pub triggered: bool,
+    pub location: String,
+    pub provider: Provider,
+    /// Temperature in degrees Celsius.
+    pub temperature: f64,
+    /// Relative humidity in percent.
+    pub humidity: Option<f64>,
+    /// Pressure in hectopascals.
+    pub pressure: Option<f64>,
+    /// Wind speed in meters per second.
+    pub wind_speed: Option<f64>,
+    pub condition: Condition,
+    pub description: String,
+}
+
+impl Event {
+    pub fn new(
+        alert: &Alert,
+        triggered: bool,
+        location: &Location,
+        provider: Provider,
+        weather: &Weather,
+    ) -> Self {
+        Self {
+            alert: alert.name.clone(),
+            rule: alert.rule.to_string(),
+            triggered,
+            location: location.to_string(),
+            provider,
+            temperature: weather.temperature.get::<degree_celsius>(),
+            humidity: weather.humidity.map(|h| h.get::<percent>()),
+            pressure: weather.pressure.map(|p| p.get::<hectopascal>()),
+            wind_speed: weather.wind_speed.map(|v| v.get::<meter_per_second>()),
+            condition: weather.condition,
+            description: weather.description.clone(),
+        }
+    }
+
+    /// Fields as `WEATHER_<FIELD>` environment variables, missing values are empty.
+    fn env(&self) -> Vec<(String, String)> {
+        let Ok(Value::Object(fields)) = serde_json::to_value(self) else {
+            unreachable!("event should be serialized as an object");
+        };
+        fields
+            .into_iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    Value::String(s) => s,
+                    Value::Null => String::new(),
+                    value => value.to_string(),
+                };
+                (format!("WEATHER_{}", key.to_uppercase()), value)
+            })
+            .collect()
+    }
+}
+
+/// Send the event to all notifications of the alert, trying every one even if some fail.
+pub fn notify(client: &Client, alert: &Alert, event: &Event) -> Result<()> {
+    let failed = |reason: String| Error::Notification {
+        alert: alert.name.clone(),
+        reason,
+    };
+    let mut result = Ok(());
+    if let Some(command) = &alert.command {
+        let status = shell(command).envs(event.env()).status();
+        let outcome = match status {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(failed(format!("\"{command}\" exited with {status}"))),
+            Err(e) => Err(failed(format!("failed to run \"{command}\": {e}"))),
+        };
+        result = result.and(outcome);
+    }
+    if let Some(url) = &alert.webhook {
+        let outcome = client
+            .post_json(url, event)
+            .map_err(|e| failed(format!("webhook failed: {e}")));
+        result = result.and(outcome);
+    }
+    if let Some(path) = &alert.file {
+        let line = serde_json::to_string(event).expect("event should be serializable");
+        let outcome = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{line}"))
+            .map_err(|e| failed(format!("failed to write {path:?}: {e}")));
+        result = result.and(outcome);
+    }
+    result
+}
+
+fn shell(command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    shell
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+    use assert_fs::NamedTempFile;
+
+    use crate::api::stub::WeatherBuilder;
+
+    use super::*;
+
+    fn weather(celsius: f64, condition: Condition) -> Weather {
+        WeatherBuilder::new(celsius)
+            .condition(condition)
+            .humidity(Ratio::new::<percent>(80.0))
+            .wind_speed(Velocity::new::<meter_per_second>(10.0))
+            .build()
+    }
+
+    fn rule(text: &str) -> Rule {
+        Rule::try_from(text.to_string()).unwrap()
+    }
+
+    #[test]
+    fn evaluate_rules() {
+        let cold = weather(-10.0, Condition::Snow);
+        let warm = weather(20.0, Condition::Thunderstorm);
+        for (text, on_cold, on_warm) in [
+            ("temp < -5", true, false),
+            ("temperature > 67F", false, true),
+            ("humidity > 75%", true, true),
+            ("wind > 15 m/s", false, false),
+            ("wind >= 36 km/h", true, true),
+            ("pressure < 1000 hPa", false, false),
+            ("condition in [thunderstorm, heavy_rain]", false, true),
+        ] {
+            let rule = rule(text);
+            assert_eq!(rule.matches(&cold), on_cold, "{text}");
+            assert_eq!(rule.matches(&warm), on_warm, "{text}");
+        }
+
+        let rule = rule("temp < -5 at @datacenter");
+        assert_eq!(rule.location(), Some("datacenter"));
+        assert_eq!(rule.to_string(), "temp < -5 at @datacenter");
+    }
+
+    #[test]
+    fn reject_invalid_rules() {
+        for text in [
+            "temp",
+            "temp = 5",
+            "temp < cold",
+            "temp < 5 parsecs",
+            "rain > 5",
+            "condition in [tornado]",
+            "condition is snow",
+            "temp < 5 at datacenter",
+        ] {
+            assert!(Rule::try_from(text.to_string()).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn notify_on_state_change_only() {
+        let mut state = State::default();
+        assert!(state.update("freeze", true));
+        assert!(!state.update("freeze", true));
+        assert!(state.is_triggered("freeze"));
+        assert!(state.update("freeze", false));
+        assert!(!state.update("freeze", false));
+
+        // Desktop notifications are recorded apart from the others.
+        assert!(state.update_shown("storm", true));
+        assert!(state.is_shown("storm"));
+        assert!(!state.is_triggered("storm"));
+        state.update("storm", true);
+        state.retain(&[]);
+        assert!(!state.is_shown("storm"));
+        assert!(!state.is_triggered("storm"));
+    }
+
+    #[test]
+    fn notify_to_file() {
+        let file = NamedTempFile::new("alerts.log").unwrap();
+        let alert = Alert {
+            name: "freeze".to_string(),
+            rule: rule("temp < -5"),
+            command: None,
+            webhook: None,
+            file: Some(file.path().to_owned()),
+        };
+        let location = Location::from_coordinates("50.45,30.52").unwrap();
+        let weather = weather(-10.0, Condition::Snow);
+        let event = Event::new(&alert, true, &location, Provider::OpenWeather, &weather);
+        let client = Client::new(&Default::default()).unwrap();
+        notify(&client, &alert, &event).unwrap();
+        notify(&client, &alert, &event).unwrap();
+
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        let lines: Vec<Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["alert"], "freeze");
+        assert_eq!(lines[0]["triggered"], true);
+        assert_eq!(lines[0]["temperature"], -10.0);
+        assert_eq!(lines[0]["pressure"], Value::Null);
+
+        let env = event.env();
+        assert!(env.contains(&("WEATHER_ALERT".to_string(), "freeze".to_string())));
+        assert!(env.contains(&("WEATHER_CONDITION".to_string(), "Snow".to_string())));
+        assert!(env.contains(&("WEATHER_PRESSURE".to_string(), String::new())));
+        file.assert(predicates::str::contains("\"rule\":\"temp < -5\""));
+    }
+}
@@ -1,15 +1,33 @@
-use reqwest::blocking::Response;
+use std::sync::Arc;
+
+use log::warn;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
 use url::{ParseError, Url};
 
 pub use accu_weather::AccuWeather;
+#[cfg(feature = "async")]
+pub use async_api::{concurrently, new_async, with_failover_async, AsyncApi, BoxFuture};
+#[cfg(feature = "async")]
+pub use client::AsyncClient;
+pub use client::{Client, ClientSettings, TlsVersion};
 pub use open_weather::OpenWeather;
+pub use quota::{Limits, Quota, Window};
 pub use weather_api::WeatherApi;
 
-use crate::data::{Location, Provider, Weather};
-use crate::error::Result;
+use crate::data::{Forecast, Language, Location, Provider, Weather};
+use crate::error::{Error, Result};
+
+use client::Response;
 
 mod accu_weather;
+#[cfg(feature = "async")]
+mod async_api;
+mod client;
 mod open_weather;
+mod quota;
+#[cfg(test)]
+pub(crate) mod stub;
 mod weather_api;
 
 pub trait Api {
@@ -19,7 +37,46 @@ pub trait Api {
 
     fn get_weather(&self, location: &Location) -> Result<Weather>;
 
+    /// Daily forecast starting today, for as many days as the free plan of the provider gives.
+    fn get_forecast(&self, location: &Location) -> Result<Vec<Forecast>>;
+
     fn provider(&self) -> Provider;
+
+    /// Resolve location, possibly found by another provider, into the one this provider can use.
+    fn resolve_location(&self, location: &Location) -> Result<Location> {
+        if location.lat.is_some() && location.lon.is_some() {
+            return Ok(location.clone());
+        }
+        // Some locations lack coordinates, so search by name as the last resort.
+        self.search_location(&location.name)?
+            .into_iter()
+            .next()
+            .ok_or(Error::LocationNotFound)
+    }
+}
+
+/// Call providers in order until one answers or fails with a non-retryable error.
+/// Return the answer along with the provider that gave it.
+pub fn with_failover<T>(
+    apis: &[Box<dyn Api>],
+    f: impl Fn(&dyn Api) -> Result<T>,
+) -> Result<(T, Provider)> {
+    let (last, rest) = apis
+        .split_last()
+        .expect("at least one provider should be given");
+    for api in rest {
+        match f(api.as_ref()) {
+            Ok(value) => return Ok((value, api.provider())),
+            Err(e) if e.is_retryable() => {
+                warn!(
+                    "\"{:?}\" provider failed, trying the next one: {e}",
+                    api.provider()
+                );
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    f(last.as_ref()).map(|value| (value, last.provider()))
 }
 
 fn construct_url(
@@ -35,20 +92,106 @@ fn construct_url(
     Ok(url)
 }
 
-fn has_valid_status_code(result: reqwest::Result<Response>, invalid_code: u16) -> Result<bool> {
-    if let Some(status_code) = result.as_ref().err().and_then(|e| e.status()) {
-        if status_code == invalid_code {
-            return Ok(false);
-        }
+fn is_valid_key(result: Result<Response>) -> Result<bool> {
+    match result {
+        Ok(_) => Ok(true),
+        Err(Error::InvalidApiKey) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn error_for_status(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        Ok(response)
+    } else {
+        Err(status_error(status))
     }
-    result?;
-    Ok(true)
 }
 
-pub fn new(provider: Provider, api_key: String) -> Box<dyn Api> {
+/// Classify error by the status code only, providers can refine it using the response body.
+fn status_error(status: StatusCode) -> Error {
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Error::InvalidApiKey,
+        StatusCode::NOT_FOUND => Error::LocationNotFound,
+        StatusCode::TOO_MANY_REQUESTS => Error::QuotaExceeded,
+        status if status.is_server_error() => Error::ProviderOutage(status.as_u16()),
+        status => Error::UnexpectedStatus(status.as_u16()),
+    }
+}
+
+/// Deserialize JSON response, keeping the path to the field that failed.
+fn parse<T: DeserializeOwned>(response: Response) -> Result<T> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(response.bytes());
+    serde_path_to_error::deserialize(deserializer).map_err(|e| Error::MalformedResponse {
+        path: e.path().to_string(),
+        reason: e.into_inner().to_string(),
+    })
+}
+
+pub fn new(
+    provider: Provider,
+    client: Arc<Client>,
+    api_key: String,
+    language: Language,
+) -> Box<dyn Api> {
     match provider {
-        Provider::OpenWeather => Box::new(OpenWeather::new(api_key)),
-        Provider::WeatherApi => Box::new(WeatherApi::new(api_key)),
-        Provider::AccuWeather => Box::new(AccuWeather::new(api_key)),
+        Provider::OpenWeather => Box::new(OpenWeather::new(client, api_key, language)),
+        Provider::WeatherApi => Box::new(WeatherApi::new(client, api_key, language)),
+        Provider::AccuWeather => Box::new(AccuWeather::new(client, api_key, language)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stub::{location, Stub};
+    use super::*;
+
+    #[test]
+    fn failover_on_retryable_errors() {
+        let apis: Vec<Box<dyn Api>> = vec![
+            Box::new(Stub::new(Provider::AccuWeather).failing(|| Error::QuotaExceeded)),
+            Box::new(Stub::new(Provider::OpenWeather).failing(|| Error::ProviderOutage(502))),
+            Box::new(Stub::new(Provider::WeatherApi)),
+        ];
+        let (_, provider) = with_failover(&apis, |api| {
+            api.get_weather(&location("Kyiv", 50.45, 30.52))
+        })
+        .unwrap();
+        assert_eq!(provider, Provider::WeatherApi);
+    }
+
+    #[test]
+    fn no_failover_on_other_errors() {
+        let apis: Vec<Box<dyn Api>> = vec![
+            Box::new(Stub::new(Provider::AccuWeather).failing(|| Error::InvalidApiKey)),
+            Box::new(Stub::new(Provider::OpenWeather)),
+        ];
+        let result = with_failover(&apis, |api| {
+            api.get_weather(&location("Kyiv", 50.45, 30.52))
+        });
+        assert!(matches!(result, Err(Error::InvalidApiKey)));
+    }
+
+    #[test]
+    fn resolve_location_by_name() {
+        let kyiv = location("Kyiv", 50.45, 30.52);
+        let api = Stub::new(Provider::OpenWeather).knowing(vec![kyiv.clone()]);
+        let without_coordinates = Location {
+            lat: None,
+            lon: None,
+            ..kyiv.clone()
+        };
+        assert_eq!(
+            Api::resolve_location(&api, &without_coordinates).unwrap(),
+            kyiv
+        );
+
+        let unknown = Location {
+            name: "Atlantis".to_string(),
+            ..without_coordinates
+        };
+        let result = Api::resolve_location(&api, &unknown);
+        assert!(matches!(result, Err(Error::LocationNotFound)));
     }
 }
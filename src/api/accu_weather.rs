@@ -1,54 +1,202 @@
-use reqwest::blocking::{get, Response};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use reqwest::StatusCode;
 use serde::Deserialize;
-use uom::si::f64::ThermodynamicTemperature;
+use uom::si::f64::{Pressure, Ratio, ThermodynamicTemperature, Velocity};
+use uom::si::pressure::millibar;
+use uom::si::ratio::percent;
 use uom::si::thermodynamic_temperature::degree_celsius;
+use uom::si::velocity::kilometer_per_hour;
+use url::Url;
 
-use crate::data::{self, Provider};
+use crate::data::{self, Condition, Language, Provider, TimeZone};
 use crate::error::{Error, Result};
 
-use super::{construct_url, has_valid_status_code, Api};
+use super::client::Response;
+use super::{construct_url, is_valid_key, parse, status_error, Api, Client};
+#[cfg(feature = "async")]
+use super::{AsyncApi, AsyncClient, BoxFuture};
 
-pub struct AccuWeather {
+pub struct AccuWeather<C = Client> {
+    client: Arc<C>,
     api_key: String,
+    language: Language,
+    resolved: Resolved,
+}
+
+impl<C> AccuWeather<C> {
+    pub fn new(client: Arc<C>, api_key: String, language: Language) -> Self {
+        Self {
+            client,
+            api_key,
+            language,
+            resolved: Resolved::default(),
+        }
+    }
+
+    fn weather_url(&self, location: &data::Location) -> Url {
+        current_conditions(
+            &self.api_key,
+            location.id.as_ref().expect("id should be set"),
+            self.language,
+        )
+    }
+
+    fn forecast_url(&self, location: &data::Location) -> Url {
+        forecasts_daily(
+            &self.api_key,
+            location.id.as_ref().expect("id should be set"),
+            self.language,
+        )
+    }
 }
 
 impl AccuWeather {
-    pub fn new(api_key: String) -> Self {
-        Self { api_key }
+    fn call(&self, url: Url) -> Result<Response> {
+        error_for_status(self.client.get(Provider::AccuWeather, url)?)
     }
 }
 
 impl Api for AccuWeather {
     fn is_valid(&self) -> Result<bool> {
-        has_valid_status_code(locations_cities_search(&self.api_key, "Kyiv"), 401)
+        is_valid_key(self.call(locations_cities_search(
+            &self.api_key,
+            "Kyiv",
+            self.language,
+        )))
     }
 
     fn search_location(&self, q: &str) -> Result<Vec<data::Location>> {
-        let response = locations_cities_search(&self.api_key, q)?;
-        let locations: Vec<Location> = response.json()?;
-        Ok(locations.into_iter().map(Into::into).collect())
+        locations(self.call(locations_cities_search(&self.api_key, q, self.language))?)
     }
 
     fn get_weather(&self, location: &data::Location) -> Result<data::Weather> {
-        let response = current_conditions(
-            &self.api_key,
-            location.id.as_ref().expect("id should be set"),
-        )?;
-        let mut weathers: Vec<Weather> = response.json()?;
-        if let Some(weather) = weathers.pop() {
-            Ok(weather.into())
-        } else {
-            Err(Error::BadResponse)
+        weather(self.call(self.weather_url(location))?, location)
+    }
+
+    fn get_forecast(&self, location: &data::Location) -> Result<Vec<data::Forecast>> {
+        forecasts(self.call(self.forecast_url(location))?)
+    }
+
+    fn provider(&self) -> Provider {
+        Provider::AccuWeather
+    }
+
+    fn resolve_location(&self, location: &data::Location) -> Result<data::Location> {
+        match (&location.id, location.lat, location.lon) {
+            (Some(_), _, _) => Ok(location.clone()),
+            (None, Some(lat), Some(lon)) => {
+                if let Some(location) = self.resolved.get(lat, lon) {
+                    return Ok(location);
+                }
+                let url =
+                    locations_cities_geoposition_search(&self.api_key, lat, lon, self.language);
+                let location: Location = parse(self.call(url)?)?;
+                Ok(self.resolved.insert(lat, lon, location.into()))
+            }
+            // Locations saved before coordinates were kept, search by name then.
+            _ => self
+                .search_location(&location.name)?
+                .into_iter()
+                .next()
+                .ok_or(Error::LocationNotFound),
         }
     }
+}
+
+#[cfg(feature = "async")]
+impl AccuWeather<AsyncClient> {
+    async fn call(&self, url: Url) -> Result<Response> {
+        error_for_status(self.client.get(Provider::AccuWeather, url).await?)
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncApi for AccuWeather<AsyncClient> {
+    fn is_valid(&self) -> BoxFuture<'_, Result<bool>> {
+        Box::pin(async move {
+            let url = locations_cities_search(&self.api_key, "Kyiv", self.language);
+            is_valid_key(self.call(url).await)
+        })
+    }
+
+    fn search_location<'a>(&'a self, q: &'a str) -> BoxFuture<'a, Result<Vec<data::Location>>> {
+        Box::pin(async move {
+            locations(
+                self.call(locations_cities_search(&self.api_key, q, self.language))
+                    .await?,
+            )
+        })
+    }
+
+    fn get_weather<'a>(
+        &'a self,
+        location: &'a data::Location,
+    ) -> BoxFuture<'a, Result<data::Weather>> {
+        Box::pin(async move { weather(self.call(self.weather_url(location)).await?, location) })
+    }
+
+    fn get_forecast<'a>(
+        &'a self,
+        location: &'a data::Location,
+    ) -> BoxFuture<'a, Result<Vec<data::Forecast>>> {
+        Box::pin(async move { forecasts(self.call(self.forecast_url(location)).await?) })
+    }
 
     fn provider(&self) -> Provider {
         Provider::AccuWeather
     }
+
+    fn resolve_location<'a>(
+        &'a self,
+        location: &'a data::Location,
+    ) -> BoxFuture<'a, Result<data::Location>> {
+        Box::pin(async move {
+            match (&location.id, location.lat, location.lon) {
+                (Some(_), _, _) => Ok(location.clone()),
+                (None, Some(lat), Some(lon)) => {
+                    if let Some(location) = self.resolved.get(lat, lon) {
+                        return Ok(location);
+                    }
+                    let url =
+                        locations_cities_geoposition_search(&self.api_key, lat, lon, self.language);
+                    let location: Location = parse(self.call(url).await?)?;
+                    Ok(self.resolved.insert(lat, lon, location.into()))
+                }
+                // Locations saved before coordinates were kept, search by name then.
+                _ => self
+                    .search_location(&location.name)
+                    .await?
+                    .into_iter()
+                    .next()
+                    .ok_or(Error::LocationNotFound),
+            }
+        })
+    }
 }
 
 const HOST: &str = "https://dataservice.accuweather.com";
 
+/// Locations found by coordinates, so every known location is searched once while the process runs,
+/// not before each call for weather.
+#[derive(Default)]
+struct Resolved(Mutex<HashMap<(u64, u64), data::Location>>);
+
+impl Resolved {
+    fn get(&self, lat: f64, lon: f64) -> Option<data::Location> {
+        let resolved = self.0.lock().expect("lock should not be poisoned");
+        resolved.get(&(lat.to_bits(), lon.to_bits())).cloned()
+    }
+
+    fn insert(&self, lat: f64, lon: f64, location: data::Location) -> data::Location {
+        let mut resolved = self.0.lock().expect("lock should not be poisoned");
+        resolved.insert((lat.to_bits(), lon.to_bits()), location.clone());
+        location
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "PascalCase")]
 struct Location {
@@ -56,6 +204,8 @@ struct Location {
     localized_name: String,
     country: Country,
     administrative_area: AdministrativeArea,
+    geo_position: GeoPosition,
+    time_zone: Option<LocationTimeZone>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -70,6 +220,19 @@ struct AdministrativeArea {
     localized_name: String,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct LocationTimeZone {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct GeoPosition {
+    latitude: f64,
+    longitude: f64,
+}
+
 impl From<Location> for data::Location {
     fn from(value: Location) -> Self {
         Self {
@@ -77,33 +240,71 @@ impl From<Location> for data::Location {
             name: value.localized_name,
             state: Some(value.administrative_area.localized_name),
             country: value.country.localized_name,
-            lat: None,
-            lon: None,
+            lat: Some(value.geo_position.latitude),
+            lon: Some(value.geo_position.longitude),
+            timezone: value.time_zone.map(|tz| TimeZone::Named(tz.name)),
         }
     }
 }
 
-fn locations_cities_search(api_key: &str, q: &str) -> reqwest::Result<Response> {
-    let url = construct_url(
+fn locations_cities_search(api_key: &str, q: &str, language: Language) -> Url {
+    construct_url(
         HOST,
         &["locations", "v1", "cities", "search"],
-        &[("apikey", api_key), ("q", q)],
+        &[("apikey", api_key), ("q", q), ("language", language.code())],
     )
-    .expect("static url should be valid");
+    .expect("static url should be valid")
+}
 
-    get(url)?.error_for_status()
+fn locations_cities_geoposition_search(
+    api_key: &str,
+    lat: f64,
+    lon: f64,
+    language: Language,
+) -> Url {
+    construct_url(
+        HOST,
+        &["locations", "v1", "cities", "geoposition", "search"],
+        &[
+            ("apikey", api_key),
+            ("q", &format!("{lat},{lon}")),
+            ("language", language.code()),
+        ],
+    )
+    .expect("static url should be valid")
+}
+
+fn locations(response: Response) -> Result<Vec<data::Location>> {
+    let locations: Vec<Location> = parse(response)?;
+    Ok(locations.into_iter().map(Into::into).collect())
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "PascalCase")]
 struct Weather {
     weather_text: String,
-    temperature: Temperature,
+    weather_icon: u8,
+    is_day_time: bool,
+    temperature: Measurement,
+    // Reported only with details.
+    relative_humidity: Option<f64>,
+    pressure: Option<Measurement>,
+    wind: Option<Wind>,
+    real_feel_temperature: Option<Measurement>,
+    dew_point: Option<Measurement>,
+    // Observation time with the offset of the location.
+    local_observation_date_time: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Wind {
+    speed: Measurement,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "PascalCase")]
-struct Temperature {
+struct Measurement {
     metric: Metric,
 }
 
@@ -119,18 +320,211 @@ impl From<Weather> for data::Weather {
             temperature: ThermodynamicTemperature::new::<degree_celsius>(
                 value.temperature.metric.value,
             ),
+            condition: condition(value.weather_icon),
+            is_day: value.is_day_time,
             description: value.weather_text,
+            humidity: value.relative_humidity.map(Ratio::new::<percent>),
+            pressure: value
+                .pressure
+                .map(|p| Pressure::new::<millibar>(p.metric.value)),
+            wind_speed: value
+                .wind
+                .map(|w| Velocity::new::<kilometer_per_hour>(w.speed.metric.value)),
+            feels_like: value
+                .real_feel_temperature
+                .map(|t| ThermodynamicTemperature::new::<degree_celsius>(t.metric.value)),
+            dew_point: value
+                .dew_point
+                .map(|t| ThermodynamicTemperature::new::<degree_celsius>(t.metric.value)),
+            observed: value
+                .local_observation_date_time
+                .map(|t| t.with_timezone(&Utc)),
+            // The named zone of the location is set by `weather`, if known.
+            timezone: value
+                .local_observation_date_time
+                .map(|t| TimeZone::Offset(t.offset().local_minus_utc())),
         }
     }
 }
 
-fn current_conditions(api_key: &str, location_key: &str) -> reqwest::Result<Response> {
-    let url = construct_url(
+// https://developer.accuweather.com/weather-icons
+fn condition(icon: u8) -> Condition {
+    match icon {
+        1 | 2 | 30 | 31 | 33 | 34 => Condition::Clear,
+        3 | 4 | 35 | 36 => Condition::PartlyCloudy,
+        6 | 7 | 38 => Condition::Cloudy,
+        8 => Condition::Overcast,
+        5 | 37 => Condition::Haze,
+        11 => Condition::Fog,
+        12 | 13 | 14 | 18 | 39 | 40 => Condition::Rain,
+        24 | 26 => Condition::FreezingRain,
+        15 | 16 | 17 | 41 | 42 => Condition::Thunderstorm,
+        19 | 20 | 21 | 22 | 23 | 43 | 44 => Condition::Snow,
+        25 | 29 => Condition::Sleet,
+        32 => Condition::Windy,
+        _ => Condition::Unknown,
+    }
+}
+
+fn current_conditions(api_key: &str, location_key: &str, language: Language) -> Url {
+    construct_url(
         HOST,
         &["currentconditions", "v1", location_key],
-        &[("apikey", api_key)],
+        &[
+            ("apikey", api_key),
+            ("language", language.code()),
+            ("details", "true"),
+        ],
+    )
+    .expect("static url should be valid")
+}
+
+fn weather(response: Response, location: &data::Location) -> Result<data::Weather> {
+    let mut weathers: Vec<Weather> = parse(response)?;
+    if let Some(weather) = weathers.pop() {
+        let mut weather: data::Weather = weather.into();
+        if let Some(TimeZone::Named(_)) = &location.timezone {
+            weather.timezone = location.timezone.clone();
+        }
+        Ok(weather)
+    } else {
+        Err(Error::MalformedResponse {
+            path: ".".to_string(),
+            reason: "empty array".to_string(),
+        })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Forecast {
+    daily_forecasts: Vec<DailyForecast>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct DailyForecast {
+    // Start of the day with the offset of the location.
+    date: DateTime<FixedOffset>,
+    temperature: Range,
+    day: Day,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Range {
+    minimum: Metric,
+    maximum: Metric,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Day {
+    icon: u8,
+    icon_phrase: String,
+}
+
+impl From<DailyForecast> for data::Forecast {
+    fn from(value: DailyForecast) -> Self {
+        Self {
+            date: value.date.date_naive(),
+            min_temperature: ThermodynamicTemperature::new::<degree_celsius>(
+                value.temperature.minimum.value,
+            ),
+            max_temperature: ThermodynamicTemperature::new::<degree_celsius>(
+                value.temperature.maximum.value,
+            ),
+            condition: condition(value.day.icon),
+            description: value.day.icon_phrase,
+        }
+    }
+}
+
+fn forecasts_daily(api_key: &str, location_key: &str, language: Language) -> Url {
+    construct_url(
+        HOST,
+        &["forecasts", "v1", "daily", "5day", location_key],
+        &[
+            ("apikey", api_key),
+            ("language", language.code()),
+            ("metric", "true"),
+        ],
     )
-    .expect("static url should be valid");
+    .expect("static url should be valid")
+}
+
+fn forecasts(response: Response) -> Result<Vec<data::Forecast>> {
+    let forecast: Forecast = parse(response)?;
+    Ok(forecast
+        .daily_forecasts
+        .into_iter()
+        .map(Into::into)
+        .collect())
+}
 
-    get(url)?.error_for_status()
+// https://developer.accuweather.com/accuweather-locations-api/apis
+fn error_for_status(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    // Exceeded quota is reported as a service outage with a specific message.
+    if status == StatusCode::SERVICE_UNAVAILABLE {
+        let text = response.text();
+        if text.contains("allowed number of requests has been exceeded") {
+            return Err(Error::QuotaExceeded);
+        }
+    }
+    Err(status_error(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_icons_to_conditions() {
+        // Every documented icon, day ones go first.
+        for (icons, expected) in [
+            (&[1, 2, 30, 31, 33, 34][..], Condition::Clear),
+            (&[3, 4, 35, 36], Condition::PartlyCloudy),
+            (&[6, 7, 38], Condition::Cloudy),
+            (&[8], Condition::Overcast),
+            (&[5, 37], Condition::Haze),
+            (&[11], Condition::Fog),
+            (&[12, 13, 14, 18, 39, 40], Condition::Rain),
+            (&[24, 26], Condition::FreezingRain),
+            (&[15, 16, 17, 41, 42], Condition::Thunderstorm),
+            (&[19, 20, 21, 22, 23, 43, 44], Condition::Snow),
+            (&[25, 29], Condition::Sleet),
+            (&[32], Condition::Windy),
+            (&[9, 10, 27, 28, 45], Condition::Unknown),
+        ] {
+            for &icon in icons {
+                assert_eq!(condition(icon), expected, "icon {icon}");
+            }
+        }
+    }
+
+    #[test]
+    fn resolve_location_once() {
+        let api = AccuWeather::new(
+            Arc::new(Client::new(&Default::default()).unwrap()),
+            "secret".to_string(),
+            Language::En,
+        );
+        let kyiv = crate::api::stub::location("Kyiv", 50.45, 30.52);
+        api.resolved.insert(
+            50.45,
+            30.52,
+            data::Location {
+                id: Some("324505".to_string()),
+                ..kyiv.clone()
+            },
+        );
+        // Found without asking the provider, which would fail with the fake key.
+        let resolved = Api::resolve_location(&api, &kyiv).unwrap();
+        assert_eq!(resolved.id.as_deref(), Some("324505"));
+        assert!(api.resolved.get(50.45, 30.53).is_none());
+    }
 }
@@ -0,0 +1,154 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures_util::stream::{self, StreamExt};
+use log::warn;
+
+use crate::data::{Forecast, Language, Location, Provider, Weather};
+use crate::error::{Error, Result};
+
+use super::{AccuWeather, AsyncClient, OpenWeather, WeatherApi};
+
+/// Future returned by [`AsyncApi`] methods, boxed so the trait can be used as `dyn AsyncApi`.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Asynchronous counterpart of [`Api`](super::Api), so many calls can be made concurrently.
+pub trait AsyncApi: Send + Sync {
+    fn is_valid(&self) -> BoxFuture<'_, Result<bool>>;
+
+    fn search_location<'a>(&'a self, q: &'a str) -> BoxFuture<'a, Result<Vec<Location>>>;
+
+    fn get_weather<'a>(&'a self, location: &'a Location) -> BoxFuture<'a, Result<Weather>>;
+
+    /// Daily forecast starting today, for as many days as the free plan of the provider gives.
+    fn get_forecast<'a>(&'a self, location: &'a Location) -> BoxFuture<'a, Result<Vec<Forecast>>>;
+
+    fn provider(&self) -> Provider;
+
+    /// Resolve location, possibly found by another provider, into the one this provider can use.
+    fn resolve_location<'a>(&'a self, location: &'a Location) -> BoxFuture<'a, Result<Location>> {
+        Box::pin(async move {
+            if location.lat.is_some() && location.lon.is_some() {
+                return Ok(location.clone());
+            }
+            // Some locations lack coordinates, so search by name as the last resort.
+            self.search_location(&location.name)
+                .await?
+                .into_iter()
+                .next()
+                .ok_or(Error::LocationNotFound)
+        })
+    }
+}
+
+pub fn new_async(
+    provider: Provider,
+    client: Arc<AsyncClient>,
+    api_key: String,
+    language: Language,
+) -> Box<dyn AsyncApi> {
+    match provider {
+        Provider::OpenWeather => Box::new(OpenWeather::new(client, api_key, language)),
+        Provider::WeatherApi => Box::new(WeatherApi::new(client, api_key, language)),
+        Provider::AccuWeather => Box::new(AccuWeather::new(client, api_key, language)),
+    }
+}
+
+/// Call providers in order until one answers or fails with a non-retryable error.
+/// Return the answer along with the provider that gave it.
+pub async fn with_failover_async<'a, T, F>(
+    apis: &'a [Box<dyn AsyncApi>],
+    f: impl Fn(&'a dyn AsyncApi) -> F,
+) -> Result<(T, Provider)>
+where
+    F: Future<Output = Result<T>>,
+{
+    let (last, rest) = apis
+        .split_last()
+        .expect("at least one provider should be given");
+    for api in rest {
+        match f(api.as_ref()).await {
+            Ok(value) => return Ok((value, api.provider())),
+            Err(e) if e.is_retryable() => {
+                warn!(
+                    "\"{:?}\" provider failed, trying the next one: {e}",
+                    api.provider()
+                );
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    f(last.as_ref()).await.map(|value| (value, last.provider()))
+}
+
+/// Run `f` for every item, at most `limit` at a time, and return results in the order of items.
+pub async fn concurrently<I, T, F>(items: I, limit: usize, f: impl FnMut(I::Item) -> F) -> Vec<T>
+where
+    I: IntoIterator,
+    F: Future<Output = T>,
+{
+    stream::iter(items)
+        .map(f)
+        .buffered(limit.max(1))
+        .collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use crate::api::stub::{location, Stub};
+
+    use super::*;
+
+    fn block_on<T>(future: impl Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn failover_on_retryable_errors() {
+        let apis: Vec<Box<dyn AsyncApi>> = vec![
+            Box::new(Stub::new(Provider::AccuWeather).failing(|| Error::QuotaExceeded)),
+            Box::new(Stub::new(Provider::WeatherApi)),
+        ];
+        let location = location("Kyiv", 50.45, 30.52);
+        let (weather, provider) =
+            block_on(with_failover_async(&apis, |api| api.get_weather(&location))).unwrap();
+        assert_eq!(provider, Provider::WeatherApi);
+        assert_eq!(weather.description, "Kyiv");
+
+        let apis: Vec<Box<dyn AsyncApi>> = vec![
+            Box::new(Stub::new(Provider::AccuWeather).failing(|| Error::InvalidApiKey)),
+            Box::new(Stub::new(Provider::WeatherApi)),
+        ];
+        let result = block_on(with_failover_async(&apis, |api| api.get_weather(&location)));
+        assert!(matches!(result, Err(Error::InvalidApiKey)));
+    }
+
+    #[test]
+    fn limit_concurrent_calls() {
+        let api = Stub::new(Provider::OpenWeather);
+        let locations: Vec<_> = (0..10)
+            .map(|i| location(&format!("Site {i}"), i.into(), 30.52))
+            .collect();
+
+        let weathers = block_on(concurrently(&locations, 3, |location| {
+            api.get_weather(location)
+        }));
+
+        // Results keep the order of locations.
+        let names: Vec<_> = weathers
+            .into_iter()
+            .map(|weather| weather.unwrap().description)
+            .collect();
+        let expected: Vec<_> = locations.iter().map(|l| l.name.clone()).collect();
+        assert_eq!(names, expected);
+        assert_eq!(api.max_running.load(Ordering::SeqCst), 3);
+    }
+}
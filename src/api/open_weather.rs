@@ -1,44 +1,148 @@
-use reqwest::blocking::{get, Response};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Timelike};
 use serde::Deserialize;
-use uom::si::f64::ThermodynamicTemperature;
+use uom::si::f64::{Pressure, Ratio, ThermodynamicTemperature, Velocity};
+use uom::si::pressure::hectopascal;
+use uom::si::ratio::percent;
 use uom::si::thermodynamic_temperature::kelvin;
+use uom::si::velocity::meter_per_second;
+use url::Url;
 
-use crate::data::{self, Provider};
+use crate::data::{self, Condition, Language, Provider, TimeZone};
 use crate::error::{Error, Result};
 
-use super::{construct_url, has_valid_status_code, Api};
+use super::client::Response;
+use super::{construct_url, error_for_status, is_valid_key, parse, Api, Client};
+#[cfg(feature = "async")]
+use super::{AsyncApi, AsyncClient, BoxFuture};
 
-pub struct OpenWeather {
+pub struct OpenWeather<C = Client> {
+    client: Arc<C>,
     api_key: String,
+    language: Language,
+}
+
+impl<C> OpenWeather<C> {
+    pub fn new(client: Arc<C>, api_key: String, language: Language) -> Self {
+        Self {
+            client,
+            api_key,
+            language,
+        }
+    }
+
+    fn locations(&self, response: Response) -> Result<Vec<data::Location>> {
+        let locations: Vec<Location> = parse(response)?;
+        Ok(locations
+            .into_iter()
+            .map(|mut location| {
+                // Geocoding API has no language parameter, but returns local names instead.
+                if let Some(name) = location.local_names.remove(self.language.code()) {
+                    location.name = name;
+                }
+                location.into()
+            })
+            .collect())
+    }
+
+    fn weather_url(&self, location: &data::Location) -> Url {
+        data_weather(
+            &self.api_key,
+            location.lat.expect("lat should be set"),
+            location.lon.expect("lon should be set"),
+            self.language,
+        )
+    }
+
+    fn forecast_url(&self, location: &data::Location) -> Url {
+        data_forecast(
+            &self.api_key,
+            location.lat.expect("lat should be set"),
+            location.lon.expect("lon should be set"),
+            self.language,
+        )
+    }
 }
 
 impl OpenWeather {
-    pub fn new(api_key: String) -> Self {
-        Self { api_key }
+    fn call(&self, url: Url) -> Result<Response> {
+        error_for_status(self.client.get(Provider::OpenWeather, url)?)
     }
 }
 
 impl Api for OpenWeather {
     fn is_valid(&self) -> Result<bool> {
-        has_valid_status_code(geo_direct(&self.api_key, "Kyiv", true), 401)
+        is_valid_key(self.call(geo_direct(&self.api_key, "Kyiv", true)))
     }
 
     fn search_location(&self, location: &str) -> Result<Vec<data::Location>> {
-        let response = geo_direct(&self.api_key, location, false)?;
-        let locations: Vec<Location> = response.json()?;
-        Ok(locations.into_iter().map(Into::into).collect())
+        self.locations(self.call(geo_direct(&self.api_key, location, false))?)
     }
 
     fn get_weather(&self, location: &data::Location) -> Result<data::Weather> {
-        let response = data_weather(
-            &self.api_key,
-            location.lat.expect("lat should be set"),
-            location.lon.expect("lon should be set"),
-        )?;
-        let weather: Weather = response.json()?;
+        let weather: Weather = parse(self.call(self.weather_url(location))?)?;
         weather.try_into()
     }
 
+    fn get_forecast(&self, location: &data::Location) -> Result<Vec<data::Forecast>> {
+        let forecast: Forecast = parse(self.call(self.forecast_url(location))?)?;
+        Ok(daily(forecast))
+    }
+
+    fn provider(&self) -> Provider {
+        Provider::OpenWeather
+    }
+}
+
+#[cfg(feature = "async")]
+impl OpenWeather<AsyncClient> {
+    async fn call(&self, url: Url) -> Result<Response> {
+        error_for_status(self.client.get(Provider::OpenWeather, url).await?)
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncApi for OpenWeather<AsyncClient> {
+    fn is_valid(&self) -> BoxFuture<'_, Result<bool>> {
+        Box::pin(
+            async move { is_valid_key(self.call(geo_direct(&self.api_key, "Kyiv", true)).await) },
+        )
+    }
+
+    fn search_location<'a>(
+        &'a self,
+        location: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<data::Location>>> {
+        Box::pin(async move {
+            self.locations(
+                self.call(geo_direct(&self.api_key, location, false))
+                    .await?,
+            )
+        })
+    }
+
+    fn get_weather<'a>(
+        &'a self,
+        location: &'a data::Location,
+    ) -> BoxFuture<'a, Result<data::Weather>> {
+        Box::pin(async move {
+            let weather: Weather = parse(self.call(self.weather_url(location)).await?)?;
+            weather.try_into()
+        })
+    }
+
+    fn get_forecast<'a>(
+        &'a self,
+        location: &'a data::Location,
+    ) -> BoxFuture<'a, Result<Vec<data::Forecast>>> {
+        Box::pin(async move {
+            let forecast: Forecast = parse(self.call(self.forecast_url(location)).await?)?;
+            Ok(daily(forecast))
+        })
+    }
+
     fn provider(&self) -> Provider {
         Provider::OpenWeather
     }
@@ -49,6 +153,8 @@ const HOST: &str = "https://api.openweathermap.org";
 #[derive(Deserialize, Debug)]
 struct Location {
     name: String,
+    #[serde(default)]
+    local_names: HashMap<String, String>,
     lat: f64,
     lon: f64,
     country: String,
@@ -64,12 +170,13 @@ impl From<Location> for data::Location {
             country: value.country,
             lat: Some(value.lat),
             lon: Some(value.lon),
+            timezone: None,
         }
     }
 }
 
-fn geo_direct(api_key: &str, q: &str, limit: bool) -> reqwest::Result<Response> {
-    let url = construct_url(
+fn geo_direct(api_key: &str, q: &str, limit: bool) -> Url {
+    construct_url(
         HOST,
         &["geo", "1.0", "direct"],
         &[
@@ -78,49 +185,254 @@ fn geo_direct(api_key: &str, q: &str, limit: bool) -> reqwest::Result<Response>
             ("limit", if limit { "1" } else { "0" }),
         ],
     )
-    .expect("static url should be valid");
-
-    get(url)?.error_for_status()
+    .expect("static url should be valid")
 }
 
 #[derive(Deserialize, Debug)]
 struct Weather {
     weather: Vec<WeatherData>,
-    main: Temperature,
+    main: Main,
+    wind: Option<Wind>,
+    // Unix time of the observation.
+    dt: Option<i64>,
+    // Offset from UTC in seconds.
+    timezone: Option<i32>,
 }
 
 #[derive(Deserialize, Debug)]
 struct WeatherData {
-    main: String,
+    id: u16,
+    description: String,
+    icon: String,
 }
 
 #[derive(Deserialize, Debug)]
-struct Temperature {
+struct Main {
     temp: f64,
+    feels_like: Option<f64>,
+    humidity: Option<f64>,
+    pressure: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Wind {
+    speed: f64,
 }
 
 impl TryFrom<Weather> for data::Weather {
     type Error = Error;
 
     fn try_from(mut value: Weather) -> std::result::Result<Self, Self::Error> {
+        let data = value
+            .weather
+            .pop()
+            .ok_or_else(|| Error::MalformedResponse {
+                path: "weather".to_string(),
+                reason: "empty array".to_string(),
+            })?;
         Ok(Self {
             temperature: ThermodynamicTemperature::new::<kelvin>(value.main.temp),
-            description: value.weather.pop().ok_or(Error::BadResponse)?.main,
+            condition: condition(data.id),
+            // Icon id ends with "d" for day and "n" for night.
+            is_day: !data.icon.ends_with('n'),
+            description: capitalize(&data.description),
+            humidity: value.main.humidity.map(Ratio::new::<percent>),
+            pressure: value.main.pressure.map(Pressure::new::<hectopascal>),
+            // Standard units are used, so the speed is in meters per second.
+            wind_speed: value
+                .wind
+                .map(|w| Velocity::new::<meter_per_second>(w.speed)),
+            feels_like: value
+                .main
+                .feels_like
+                .map(ThermodynamicTemperature::new::<kelvin>),
+            dew_point: None,
+            observed: value.dt.and_then(|dt| DateTime::from_timestamp(dt, 0)),
+            timezone: value.timezone.map(TimeZone::Offset),
         })
     }
 }
 
-fn data_weather(api_key: &str, lat: f64, lon: f64) -> reqwest::Result<Response> {
-    let url = construct_url(
+// Descriptions are lowercase, unlike other providers.
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// https://openweathermap.org/weather-conditions
+fn condition(id: u16) -> Condition {
+    match id {
+        200..=232 => Condition::Thunderstorm,
+        300..=321 => Condition::Drizzle,
+        500 | 501 | 520 | 521 | 531 => Condition::Rain,
+        502..=504 | 522 => Condition::HeavyRain,
+        511 => Condition::FreezingRain,
+        600 | 601 | 620 | 621 => Condition::Snow,
+        602 | 622 => Condition::HeavySnow,
+        611..=616 => Condition::Sleet,
+        701 => Condition::Mist,
+        741 => Condition::Fog,
+        711 | 721 | 731 | 751 | 761 | 762 => Condition::Haze,
+        771 | 781 => Condition::Windy,
+        800 => Condition::Clear,
+        801 | 802 => Condition::PartlyCloudy,
+        803 => Condition::Cloudy,
+        804 => Condition::Overcast,
+        _ => Condition::Unknown,
+    }
+}
+
+fn data_weather(api_key: &str, lat: f64, lon: f64, language: Language) -> Url {
+    construct_url(
         HOST,
         &["data", "2.5", "weather"],
         &[
             ("appid", api_key),
             ("lat", &lat.to_string()),
             ("lon", &lon.to_string()),
+            ("lang", language.code()),
         ],
     )
-    .expect("static url should be valid");
+    .expect("static url should be valid")
+}
+
+#[derive(Deserialize, Debug)]
+struct Forecast {
+    // Every three hours, five days ahead.
+    list: Vec<ForecastEntry>,
+    city: City,
+}
+
+#[derive(Deserialize, Debug)]
+struct ForecastEntry {
+    dt: i64,
+    main: Main,
+    weather: Vec<WeatherData>,
+}
 
-    get(url)?.error_for_status()
+#[derive(Deserialize, Debug)]
+struct City {
+    // Offset from UTC in seconds.
+    timezone: i32,
+}
+
+/// Group three-hour entries by the local date, the condition of a day is the one closest to noon.
+fn daily(forecast: Forecast) -> Vec<data::Forecast> {
+    let offset = Duration::seconds(forecast.city.timezone.into());
+    let mut days: Vec<(data::Forecast, u32)> = Vec::new();
+    for entry in forecast.list {
+        let (Some(time), Some(data)) = (
+            DateTime::from_timestamp(entry.dt, 0),
+            entry.weather.into_iter().next(),
+        ) else {
+            continue;
+        };
+        let local = time.naive_utc() + offset;
+        let temperature = ThermodynamicTemperature::new::<kelvin>(entry.main.temp);
+        let distance = local.hour().abs_diff(12);
+        match days.last_mut() {
+            Some((day, closest)) if day.date == local.date() => {
+                day.min_temperature = day.min_temperature.min(temperature);
+                day.max_temperature = day.max_temperature.max(temperature);
+                if distance < *closest {
+                    day.condition = condition(data.id);
+                    day.description = capitalize(&data.description);
+                    *closest = distance;
+                }
+            }
+            _ => days.push((
+                data::Forecast {
+                    date: local.date(),
+                    min_temperature: temperature,
+                    max_temperature: temperature,
+                    condition: condition(data.id),
+                    description: capitalize(&data.description),
+                },
+                distance,
+            )),
+        }
+    }
+    days.into_iter().map(|(day, _)| day).collect()
+}
+
+fn data_forecast(api_key: &str, lat: f64, lon: f64, language: Language) -> Url {
+    construct_url(
+        HOST,
+        &["data", "2.5", "forecast"],
+        &[
+            ("appid", api_key),
+            ("lat", &lat.to_string()),
+            ("lon", &lon.to_string()),
+            ("lang", language.code()),
+        ],
+    )
+    .expect("static url should be valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn map_ids_to_conditions() {
+        // Every documented condition id.
+        for (ids, expected) in [
+            (
+                &[200, 201, 202, 210, 211, 212, 221, 230, 231, 232][..],
+                Condition::Thunderstorm,
+            ),
+            (
+                &[300, 301, 302, 310, 311, 312, 313, 314, 321],
+                Condition::Drizzle,
+            ),
+            (&[500, 501, 520, 521, 531], Condition::Rain),
+            (&[502, 503, 504, 522], Condition::HeavyRain),
+            (&[511], Condition::FreezingRain),
+            (&[600, 601, 620, 621], Condition::Snow),
+            (&[602, 622], Condition::HeavySnow),
+            (&[611, 612, 613, 615, 616], Condition::Sleet),
+            (&[701], Condition::Mist),
+            (&[741], Condition::Fog),
+            (&[711, 721, 731, 751, 761, 762], Condition::Haze),
+            (&[771, 781], Condition::Windy),
+            (&[800], Condition::Clear),
+            (&[801, 802], Condition::PartlyCloudy),
+            (&[803], Condition::Cloudy),
+            (&[804], Condition::Overcast),
+            (&[100, 900], Condition::Unknown),
+        ] {
+            for &id in ids {
+                assert_eq!(condition(id), expected, "id {id}");
+            }
+        }
+    }
+
+    #[test]
+    fn group_forecast_by_local_day() {
+        let forecast: Forecast = serde_json::from_value(serde_json::json!({
+            "list": [
+                {"dt": 1718902800, "main": {"temp": 290.0}, "weather": [{"id": 800, "description": "clear sky", "icon": "01n"}]},
+                {"dt": 1718960400, "main": {"temp": 300.0}, "weather": [{"id": 500, "description": "light rain", "icon": "10d"}]},
+                {"dt": 1718971200, "main": {"temp": 298.0}, "weather": [{"id": 803, "description": "broken clouds", "icon": "04d"}]},
+                {"dt": 1718989200, "main": {"temp": 288.0}, "weather": [{"id": 804, "description": "overcast clouds", "icon": "04n"}]}
+            ],
+            "city": {"timezone": 10800}
+        }))
+        .unwrap();
+        let days = daily(forecast);
+        assert_eq!(days.len(), 2);
+        // 17:00 UTC is 20:00 in Kyiv, still the same day.
+        assert_eq!(days[0].date, NaiveDate::from_ymd_opt(2024, 6, 20).unwrap());
+        assert_eq!(days[1].date, NaiveDate::from_ymd_opt(2024, 6, 21).unwrap());
+        assert_eq!(days[1].min_temperature.get::<kelvin>(), 288.0);
+        assert_eq!(days[1].max_temperature.get::<kelvin>(), 300.0);
+        // 12:00 local is closer to noon than 15:00.
+        assert_eq!(days[1].condition, Condition::Rain);
+        assert_eq!(days[1].description, "Light rain");
+    }
 }
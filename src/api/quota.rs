@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{debug, warn};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+use crate::data::Provider;
+use crate::error::{Error, Result};
+use crate::storage;
+
+/// Maximum number of calls to the provider API, `None` means unlimited.
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Default, PartialEq)]
+pub struct Limits {
+    pub daily: Option<u32>,
+    pub minute: Option<u32>,
+}
+
+impl Limits {
+    /// Limits of the free plans.
+    pub fn default_for(provider: Provider) -> Self {
+        match provider {
+            Provider::OpenWeather => Self {
+                daily: None,
+                minute: Some(60),
+            },
+            Provider::WeatherApi => Self::default(),
+            Provider::AccuWeather => Self {
+                daily: Some(50),
+                minute: None,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq)]
+pub enum Window {
+    Day,
+    Minute,
+}
+
+impl Display for Window {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Window::Day => write!(f, "day"),
+            Window::Minute => write!(f, "minute"),
+        }
+    }
+}
+
+/// Calls made to the provider API during the current day and minute (UTC).
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Usage {
+    kind: Provider,
+    day: u64,
+    pub daily_calls: u32,
+    minute: u64,
+    pub minute_calls: u32,
+}
+
+impl Usage {
+    fn new(kind: Provider) -> Self {
+        Self {
+            kind,
+            day: 0,
+            daily_calls: 0,
+            minute: 0,
+            minute_calls: 0,
+        }
+    }
+
+    /// Start counting again if the day or minute has passed.
+    fn roll(&mut self, now: u64) {
+        if self.day != now / 86400 {
+            self.day = now / 86400;
+            self.daily_calls = 0;
+        }
+        if self.minute != now / 60 {
+            self.minute = now / 60;
+            self.minute_calls = 0;
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Debug)]
+struct Usages {
+    providers: Vec<Usage>,
+}
+
+impl Usages {
+    /// Usage of the provider during the current day and minute.
+    fn of(&mut self, kind: Provider) -> &mut Usage {
+        let index = match self.providers.iter().position(|u| u.kind == kind) {
+            Some(index) => index,
+            None => {
+                self.providers.push(Usage::new(kind));
+                self.providers.len() - 1
+            }
+        };
+        let usage = &mut self.providers[index];
+        usage.roll(now());
+        usage
+    }
+}
+
+/// Accounting of calls to provider APIs, persisted between runs.
+pub struct Quota {
+    path: PathBuf,
+    limits: HashMap<Provider, Limits>,
+    usages: Mutex<Usages>,
+}
+
+impl Quota {
+    pub fn load(path: PathBuf, limits: HashMap<Provider, Limits>) -> Result<Self> {
+        let usages = storage::read_file(&path)?;
+        Ok(Self {
+            path,
+            limits,
+            usages: Mutex::new(usages),
+        })
+    }
+
+    pub fn limits(&self, kind: Provider) -> Limits {
+        self.limits
+            .get(&kind)
+            .copied()
+            .unwrap_or_else(|| Limits::default_for(kind))
+    }
+
+    pub fn usage(&self, kind: Provider) -> Usage {
+        self.with_usage(kind, |usage| usage.clone())
+    }
+
+    /// Refuse the call beforehand if it would exceed the budget.
+    pub fn check(&self, kind: Provider) -> Result<()> {
+        let limits = self.limits(kind);
+        let usage = {
+            let mut usages = self.usages.lock().expect("lock should not be poisoned");
+            if let Err(e) = self.reload(&mut usages) {
+                warn!("failed to read quota usage: {e}");
+            }
+            usages.of(kind).clone()
+        };
+        let windows = [
+            (Window::Day, limits.daily, usage.daily_calls),
+            (Window::Minute, limits.minute, usage.minute_calls),
+        ];
+        for (window, limit, calls) in windows {
+            if let Some(limit) = limit.filter(|&limit| calls >= limit) {
+                return Err(Error::BudgetExceeded {
+                    provider: kind,
+                    limit,
+                    window,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Count the call and persist the usage right away, so it is not lost on exit.
+    pub fn record(&self, kind: Provider, headers: &HeaderMap) {
+        let reported = reported_daily_calls(headers);
+        let mut usages = self.usages.lock().expect("lock should not be poisoned");
+        let lock = self.reload(&mut usages);
+        let usage = usages.of(kind);
+        usage.daily_calls += 1;
+        usage.minute_calls += 1;
+        // Provider knows better, e.g. when the key is shared with others.
+        if let Some(calls) = reported {
+            usage.daily_calls = calls;
+        }
+        debug!(
+            "\"{kind:?}\" provider was called {} times today",
+            usage.daily_calls
+        );
+        if let Err(e) = lock.and_then(|_lock| storage::write_file(&self.path, &*usages)) {
+            warn!("failed to store quota usage: {e}");
+        }
+    }
+
+    /// Parallel processes share the budget, so the usage is read again under the lock,
+    /// which is returned to keep it while the usage is updated.
+    fn reload(&self, usages: &mut Usages) -> Result<File> {
+        let lock = storage::lock(&self.path)?;
+        *usages = storage::read_file(&self.path)?;
+        Ok(lock)
+    }
+
+    fn with_usage<T>(&self, kind: Provider, f: impl FnOnce(&mut Usage) -> T) -> T {
+        let mut usages = self.usages.lock().expect("lock should not be poisoned");
+        f(usages.of(kind))
+    }
+}
+
+/// Parse calls made during the day from rate limit headers, e.g. AccuWeather sends them.
+fn reported_daily_calls(headers: &HeaderMap) -> Option<u32> {
+    let header = |name: &str| -> Option<u32> {
+        [name.to_string(), format!("x-{name}")]
+            .iter()
+            .find_map(|name| headers.get(name)?.to_str().ok()?.trim().parse().ok())
+    };
+    let limit = header("ratelimit-limit")?;
+    let remaining = header("ratelimit-remaining")?;
+    Some(limit.saturating_sub(remaining))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::NamedTempFile;
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn refuse_calls_over_budget() {
+        let file = NamedTempFile::new("quota").unwrap();
+        let limits = [(
+            Provider::AccuWeather,
+            Limits {
+                daily: Some(2),
+                minute: None,
+            },
+        )];
+        let quota = Quota::load(file.to_path_buf(), limits.into()).unwrap();
+
+        for _ in 0..2 {
+            quota.check(Provider::AccuWeather).unwrap();
+            quota.record(Provider::AccuWeather, &HeaderMap::new());
+        }
+        assert!(matches!(
+            quota.check(Provider::AccuWeather),
+            Err(Error::BudgetExceeded {
+                limit: 2,
+                window: Window::Day,
+                ..
+            })
+        ));
+
+        // Default limits are used for other providers.
+        assert_eq!(quota.limits(Provider::OpenWeather).minute, Some(60));
+        quota.check(Provider::OpenWeather).unwrap();
+
+        // Usage is persisted.
+        let quota = Quota::load(file.to_path_buf(), HashMap::new()).unwrap();
+        assert_eq!(quota.usage(Provider::AccuWeather).daily_calls, 2);
+    }
+
+    #[test]
+    fn count_calls_of_parallel_processes() {
+        let file = NamedTempFile::new("quota").unwrap();
+        let first = Quota::load(file.to_path_buf(), HashMap::new()).unwrap();
+        let second = Quota::load(file.to_path_buf(), HashMap::new()).unwrap();
+        for quota in [&first, &second, &first] {
+            quota.record(Provider::WeatherApi, &HeaderMap::new());
+        }
+        assert_eq!(first.usage(Provider::WeatherApi).daily_calls, 3);
+
+        let quota = Quota::load(file.to_path_buf(), HashMap::new()).unwrap();
+        assert_eq!(quota.usage(Provider::WeatherApi).daily_calls, 3);
+
+        // Calls of others count against the budget too.
+        let limits = [(
+            Provider::WeatherApi,
+            Limits {
+                daily: Some(4),
+                minute: None,
+            },
+        )];
+        let limited = Quota::load(file.to_path_buf(), limits.into()).unwrap();
+        limited.check(Provider::WeatherApi).unwrap();
+        second.record(Provider::WeatherApi, &HeaderMap::new());
+        assert!(limited.check(Provider::WeatherApi).is_err());
+    }
+
+    #[test]
+    fn reconcile_with_rate_limit_headers() {
+        let file = NamedTempFile::new("quota").unwrap();
+        let quota = Quota::load(file.to_path_buf(), HashMap::new()).unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("RateLimit-Limit", HeaderValue::from_static("50"));
+        headers.insert("RateLimit-Remaining", HeaderValue::from_static("20"));
+        quota.record(Provider::AccuWeather, &headers);
+
+        let usage = quota.usage(Provider::AccuWeather);
+        assert_eq!(usage.daily_calls, 30);
+        assert_eq!(usage.minute_calls, 1);
+    }
+}
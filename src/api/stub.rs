@@ -0,0 +1,212 @@
+//! Provider and weather for tests, which need no network.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "async")]
+use std::time::Duration;
+
+use chrono::{Days, Utc};
+use uom::si::f64::{Ratio, ThermodynamicTemperature, Velocity};
+use uom::si::ratio::percent;
+use uom::si::thermodynamic_temperature::degree_celsius;
+
+use super::Api;
+#[cfg(feature = "async")]
+use super::{AsyncApi, BoxFuture};
+use crate::data::{Condition, Forecast, Location, Provider, Weather};
+use crate::error::{Error, Result};
+
+/// Clear weather without any values but the temperature, others are set one by one.
+pub struct WeatherBuilder(Weather);
+
+impl WeatherBuilder {
+    pub fn new(celsius: f64) -> Self {
+        Self(Weather {
+            temperature: ThermodynamicTemperature::new::<degree_celsius>(celsius),
+            condition: Condition::Clear,
+            is_day: true,
+            description: "Clear".to_string(),
+            humidity: None,
+            pressure: None,
+            wind_speed: None,
+            feels_like: None,
+            dew_point: None,
+            observed: None,
+            timezone: None,
+        })
+    }
+
+    pub fn condition(mut self, condition: Condition) -> Self {
+        self.0.condition = condition;
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.0.description = description.to_string();
+        self
+    }
+
+    pub fn humidity(mut self, humidity: Ratio) -> Self {
+        self.0.humidity = Some(humidity);
+        self
+    }
+
+    pub fn wind_speed(mut self, wind_speed: Velocity) -> Self {
+        self.0.wind_speed = Some(wind_speed);
+        self
+    }
+
+    pub fn dew_point(mut self, dew_point: ThermodynamicTemperature) -> Self {
+        self.0.dew_point = Some(dew_point);
+        self
+    }
+
+    pub fn build(self) -> Weather {
+        self.0
+    }
+}
+
+/// Location in Ukraine with the given coordinates.
+pub fn location(name: &str, lat: f64, lon: f64) -> Location {
+    Location {
+        id: None,
+        name: name.to_string(),
+        state: None,
+        country: "UA".to_string(),
+        lat: Some(lat),
+        lon: Some(lon),
+        timezone: None,
+    }
+}
+
+/// Provider that answers with the given error or reports the latitude as temperature,
+/// the name of the location as description and 50% humidity.
+/// Its forecast is three days starting today, 5 degrees below and above the latitude.
+pub struct Stub {
+    provider: Provider,
+    error: Option<fn() -> Error>,
+    /// Locations found by their exact names.
+    locations: Vec<Location>,
+    /// Number of calls for weather and forecasts, shared to be checked after the stub is boxed.
+    pub calls: Arc<AtomicUsize>,
+    #[cfg(feature = "async")]
+    running: AtomicUsize,
+    /// Most calls for weather that have been running at once.
+    #[cfg(feature = "async")]
+    pub max_running: AtomicUsize,
+}
+
+impl Stub {
+    pub fn new(provider: Provider) -> Self {
+        Self {
+            provider,
+            error: None,
+            locations: Vec::new(),
+            calls: Arc::default(),
+            #[cfg(feature = "async")]
+            running: AtomicUsize::new(0),
+            #[cfg(feature = "async")]
+            max_running: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn failing(self, error: fn() -> Error) -> Self {
+        Self {
+            error: Some(error),
+            ..self
+        }
+    }
+
+    pub fn knowing(self, locations: Vec<Location>) -> Self {
+        Self { locations, ..self }
+    }
+
+    fn search(&self, q: &str) -> Vec<Location> {
+        self.locations
+            .iter()
+            .filter(|location| location.name == q)
+            .cloned()
+            .collect()
+    }
+
+    fn weather(&self, location: &Location) -> Result<Weather> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if let Some(error) = self.error {
+            return Err(error());
+        }
+        Ok(WeatherBuilder::new(location.lat.unwrap_or_default())
+            .description(&location.name)
+            .humidity(Ratio::new::<percent>(50.0))
+            .build())
+    }
+
+    fn forecast(&self, location: &Location) -> Result<Vec<Forecast>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if let Some(error) = self.error {
+            return Err(error());
+        }
+        let celsius = location.lat.unwrap_or_default();
+        let today = Utc::now().date_naive();
+        Ok((0..3)
+            .map(|day| Forecast {
+                date: today + Days::new(day),
+                min_temperature: ThermodynamicTemperature::new::<degree_celsius>(celsius - 5.0),
+                max_temperature: ThermodynamicTemperature::new::<degree_celsius>(celsius + 5.0),
+                condition: Condition::Clear,
+                description: location.name.clone(),
+            })
+            .collect())
+    }
+}
+
+impl Api for Stub {
+    fn is_valid(&self) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn search_location(&self, q: &str) -> Result<Vec<Location>> {
+        Ok(self.search(q))
+    }
+
+    fn get_weather(&self, location: &Location) -> Result<Weather> {
+        self.weather(location)
+    }
+
+    fn get_forecast(&self, location: &Location) -> Result<Vec<Forecast>> {
+        self.forecast(location)
+    }
+
+    fn provider(&self) -> Provider {
+        self.provider
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncApi for Stub {
+    fn is_valid(&self) -> BoxFuture<'_, Result<bool>> {
+        Box::pin(async { Ok(true) })
+    }
+
+    fn search_location<'a>(&'a self, q: &'a str) -> BoxFuture<'a, Result<Vec<Location>>> {
+        Box::pin(async move { Ok(self.search(q)) })
+    }
+
+    fn get_weather<'a>(&'a self, location: &'a Location) -> BoxFuture<'a, Result<Weather>> {
+        Box::pin(async move {
+            // Answer after a while, so concurrent calls overlap.
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            self.weather(location)
+        })
+    }
+
+    fn get_forecast<'a>(&'a self, location: &'a Location) -> BoxFuture<'a, Result<Vec<Forecast>>> {
+        Box::pin(async move { self.forecast(location) })
+    }
+
+    fn provider(&self) -> Provider {
+        self.provider
+    }
+}
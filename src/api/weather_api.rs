@@ -1,42 +1,116 @@
-use reqwest::blocking::{get, Response};
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDate};
 use serde::Deserialize;
-use uom::si::f64::ThermodynamicTemperature;
+use uom::si::f64::{Pressure, Ratio, ThermodynamicTemperature, Velocity};
+use uom::si::pressure::millibar;
+use uom::si::ratio::percent;
 use uom::si::thermodynamic_temperature::degree_celsius;
+use uom::si::velocity::kilometer_per_hour;
+use url::Url;
 
-use crate::data::{self, Provider};
-use crate::error::Result;
+use crate::data::{self, Language, Provider, TimeZone};
+use crate::error::{Error, Result};
 
-use super::{construct_url, has_valid_status_code, Api};
+use super::client::Response;
+use super::{construct_url, is_valid_key, parse, status_error, Api, Client};
+#[cfg(feature = "async")]
+use super::{AsyncApi, AsyncClient, BoxFuture};
 
-pub struct WeatherApi {
+pub struct WeatherApi<C = Client> {
+    client: Arc<C>,
     api_key: String,
+    language: Language,
+}
+
+impl<C> WeatherApi<C> {
+    pub fn new(client: Arc<C>, api_key: String, language: Language) -> Self {
+        Self {
+            client,
+            api_key,
+            language,
+        }
+    }
+
+    fn weather_url(&self, location: &data::Location) -> Url {
+        current(
+            &self.api_key,
+            location.lat.expect("lat should be set"),
+            location.lon.expect("lon should be set"),
+            self.language,
+        )
+    }
+
+    fn forecast_url(&self, location: &data::Location) -> Url {
+        forecast(
+            &self.api_key,
+            location.lat.expect("lat should be set"),
+            location.lon.expect("lon should be set"),
+            self.language,
+        )
+    }
 }
 
 impl WeatherApi {
-    pub fn new(api_key: String) -> Self {
-        Self { api_key }
+    fn call(&self, url: Url) -> Result<Response> {
+        error_for_status(self.client.get(Provider::WeatherApi, url)?)
     }
 }
 
 impl Api for WeatherApi {
     fn is_valid(&self) -> Result<bool> {
-        has_valid_status_code(search(&self.api_key, "Kyiv"), 403)
+        is_valid_key(self.call(search(&self.api_key, "Kyiv")))
     }
 
     fn search_location(&self, location: &str) -> Result<Vec<data::Location>> {
-        let response = search(&self.api_key, location)?;
-        let locations: Vec<Location> = response.json()?;
-        Ok(locations.into_iter().map(Into::into).collect())
+        locations(self.call(search(&self.api_key, location))?)
     }
 
     fn get_weather(&self, location: &data::Location) -> Result<data::Weather> {
-        let response = current(
-            &self.api_key,
-            location.lat.expect("lat should be set"),
-            location.lon.expect("lon should be set"),
-        )?;
-        let weather: Weather = response.json()?;
-        Ok(weather.into())
+        weather(self.call(self.weather_url(location))?)
+    }
+
+    fn get_forecast(&self, location: &data::Location) -> Result<Vec<data::Forecast>> {
+        forecasts(self.call(self.forecast_url(location))?)
+    }
+
+    fn provider(&self) -> Provider {
+        Provider::WeatherApi
+    }
+}
+
+#[cfg(feature = "async")]
+impl WeatherApi<AsyncClient> {
+    async fn call(&self, url: Url) -> Result<Response> {
+        error_for_status(self.client.get(Provider::WeatherApi, url).await?)
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncApi for WeatherApi<AsyncClient> {
+    fn is_valid(&self) -> BoxFuture<'_, Result<bool>> {
+        Box::pin(async move { is_valid_key(self.call(search(&self.api_key, "Kyiv")).await) })
+    }
+
+    fn search_location<'a>(
+        &'a self,
+        location: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<data::Location>>> {
+        Box::pin(async move { locations(self.call(search(&self.api_key, location)).await?) })
+    }
+
+    fn get_weather<'a>(
+        &'a self,
+        location: &'a data::Location,
+    ) -> BoxFuture<'a, Result<data::Weather>> {
+        Box::pin(async move { weather(self.call(self.weather_url(location)).await?) })
+    }
+
+    fn get_forecast<'a>(
+        &'a self,
+        location: &'a data::Location,
+    ) -> BoxFuture<'a, Result<Vec<data::Forecast>>> {
+        Box::pin(async move { forecasts(self.call(self.forecast_url(location)).await?) })
     }
 
     fn provider(&self) -> Provider {
@@ -64,49 +138,236 @@ impl From<Location> for data::Location {
             country: value.country,
             lat: Some(value.lat),
             lon: Some(value.lon),
+            timezone: None,
         }
     }
 }
 
-fn search(api_key: &str, q: &str) -> reqwest::Result<Response> {
-    let url = construct_url(HOST, &["v1", "search.json"], &[("key", api_key), ("q", q)])
-        .expect("static url should be valid");
+fn search(api_key: &str, q: &str) -> Url {
+    construct_url(HOST, &["v1", "search.json"], &[("key", api_key), ("q", q)])
+        .expect("static url should be valid")
+}
 
-    get(url)?.error_for_status()
+fn locations(response: Response) -> Result<Vec<data::Location>> {
+    let locations: Vec<Location> = parse(response)?;
+    Ok(locations.into_iter().map(Into::into).collect())
 }
 
 #[derive(Deserialize, Debug)]
 struct Weather {
+    location: Option<WeatherLocation>,
     current: Current,
 }
 
+#[derive(Deserialize, Debug)]
+struct WeatherLocation {
+    tz_id: String,
+}
+
 #[derive(Deserialize, Debug)]
 struct Current {
     temp_c: f64,
+    is_day: u8,
     condition: Condition,
+    humidity: Option<f64>,
+    pressure_mb: Option<f64>,
+    wind_kph: Option<f64>,
+    feelslike_c: Option<f64>,
+    dewpoint_c: Option<f64>,
+    last_updated_epoch: Option<i64>,
 }
 
 #[derive(Deserialize, Debug)]
 struct Condition {
     text: String,
+    code: u16,
 }
 
 impl From<Weather> for data::Weather {
     fn from(value: Weather) -> Self {
         Self {
             temperature: ThermodynamicTemperature::new::<degree_celsius>(value.current.temp_c),
+            condition: condition(value.current.condition.code),
+            is_day: value.current.is_day == 1,
             description: value.current.condition.text,
+            humidity: value.current.humidity.map(Ratio::new::<percent>),
+            pressure: value.current.pressure_mb.map(Pressure::new::<millibar>),
+            wind_speed: value
+                .current
+                .wind_kph
+                .map(Velocity::new::<kilometer_per_hour>),
+            feels_like: value
+                .current
+                .feelslike_c
+                .map(ThermodynamicTemperature::new::<degree_celsius>),
+            dew_point: value
+                .current
+                .dewpoint_c
+                .map(ThermodynamicTemperature::new::<degree_celsius>),
+            observed: value
+                .current
+                .last_updated_epoch
+                .and_then(|epoch| DateTime::from_timestamp(epoch, 0)),
+            timezone: value.location.map(|l| TimeZone::Named(l.tz_id)),
         }
     }
 }
 
-fn current(api_key: &str, lat: f64, lon: f64) -> reqwest::Result<Response> {
-    let url = construct_url(
-        HOST,
-        &["v1", "current.json"],
-        &[("key", api_key), ("q", &format!("{lat},{lon}"))],
-    )
-    .expect("static url should be valid");
+// https://www.weatherapi.com/docs/weather_conditions.json
+fn condition(code: u16) -> data::Condition {
+    match code {
+        1000 => data::Condition::Clear,
+        1003 => data::Condition::PartlyCloudy,
+        1006 => data::Condition::Cloudy,
+        1009 => data::Condition::Overcast,
+        1030 => data::Condition::Mist,
+        1135 | 1147 => data::Condition::Fog,
+        1150 | 1153 => data::Condition::Drizzle,
+        1063 | 1180 | 1183 | 1186 | 1189 | 1240 => data::Condition::Rain,
+        1192 | 1195 | 1243 | 1246 => data::Condition::HeavyRain,
+        1072 | 1168 | 1171 | 1198 | 1201 => data::Condition::FreezingRain,
+        1087 | 1273 | 1276 | 1279 | 1282 => data::Condition::Thunderstorm,
+        1066 | 1114 | 1210 | 1213 | 1216 | 1219 | 1255 => data::Condition::Snow,
+        1117 | 1222 | 1225 | 1258 => data::Condition::HeavySnow,
+        1069 | 1204 | 1207 | 1249 | 1252 => data::Condition::Sleet,
+        1237 | 1261 | 1264 => data::Condition::Hail,
+        _ => data::Condition::Unknown,
+    }
+}
+
+fn current(api_key: &str, lat: f64, lon: f64, language: Language) -> Url {
+    let q = format!("{lat},{lon}");
+    let mut query_pairs = vec![("key", api_key), ("q", &q)];
+    // English is the default one and is not in the list of supported languages.
+    if language != Language::En {
+        query_pairs.push(("lang", language.code()));
+    }
+    construct_url(HOST, &["v1", "current.json"], &query_pairs).expect("static url should be valid")
+}
+
+fn weather(response: Response) -> Result<data::Weather> {
+    let weather: Weather = parse(response)?;
+    Ok(weather.into())
+}
 
-    get(url)?.error_for_status()
+#[derive(Deserialize, Debug)]
+struct Forecast {
+    forecast: ForecastDays,
+}
+
+#[derive(Deserialize, Debug)]
+struct ForecastDays {
+    forecastday: Vec<ForecastDay>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ForecastDay {
+    date: NaiveDate,
+    day: Day,
+}
+
+#[derive(Deserialize, Debug)]
+struct Day {
+    maxtemp_c: f64,
+    mintemp_c: f64,
+    condition: Condition,
+}
+
+impl From<ForecastDay> for data::Forecast {
+    fn from(value: ForecastDay) -> Self {
+        Self {
+            date: value.date,
+            min_temperature: ThermodynamicTemperature::new::<degree_celsius>(value.day.mintemp_c),
+            max_temperature: ThermodynamicTemperature::new::<degree_celsius>(value.day.maxtemp_c),
+            condition: condition(value.day.condition.code),
+            description: value.day.condition.text,
+        }
+    }
+}
+
+// The free plan gives three days at most.
+fn forecast(api_key: &str, lat: f64, lon: f64, language: Language) -> Url {
+    let q = format!("{lat},{lon}");
+    let mut query_pairs = vec![("key", api_key), ("q", &q), ("days", "3")];
+    if language != Language::En {
+        query_pairs.push(("lang", language.code()));
+    }
+    construct_url(HOST, &["v1", "forecast.json"], &query_pairs).expect("static url should be valid")
+}
+
+fn forecasts(response: Response) -> Result<Vec<data::Forecast>> {
+    let forecast: Forecast = parse(response)?;
+    Ok(forecast
+        .forecast
+        .forecastday
+        .into_iter()
+        .map(Into::into)
+        .collect())
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    error: ErrorData,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorData {
+    code: u16,
+}
+
+// https://www.weatherapi.com/docs/#intro-error-codes
+fn error_for_status(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    // Most errors are reported with 400 or 403 status codes, so the error code should be checked.
+    let code = serde_json::from_slice::<ErrorResponse>(response.bytes()).map(|r| r.error.code);
+    Err(match code {
+        Ok(1006) => Error::LocationNotFound,
+        Ok(1002 | 2006 | 2008 | 2009) => Error::InvalidApiKey,
+        Ok(2007) => Error::QuotaExceeded,
+        _ => status_error(status),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_codes_to_conditions() {
+        // Every documented condition code.
+        for (codes, expected) in [
+            (&[1000][..], data::Condition::Clear),
+            (&[1003], data::Condition::PartlyCloudy),
+            (&[1006], data::Condition::Cloudy),
+            (&[1009], data::Condition::Overcast),
+            (&[1030], data::Condition::Mist),
+            (&[1135, 1147], data::Condition::Fog),
+            (&[1150, 1153], data::Condition::Drizzle),
+            (&[1063, 1180, 1183, 1186, 1189, 1240], data::Condition::Rain),
+            (&[1192, 1195, 1243, 1246], data::Condition::HeavyRain),
+            (
+                &[1072, 1168, 1171, 1198, 1201],
+                data::Condition::FreezingRain,
+            ),
+            (
+                &[1087, 1273, 1276, 1279, 1282],
+                data::Condition::Thunderstorm,
+            ),
+            (
+                &[1066, 1114, 1210, 1213, 1216, 1219, 1255],
+                data::Condition::Snow,
+            ),
+            (&[1117, 1222, 1225, 1258], data::Condition::HeavySnow),
+            (&[1069, 1204, 1207, 1249, 1252], data::Condition::Sleet),
+            (&[1237, 1261, 1264], data::Condition::Hail),
+            (&[999, 1300], data::Condition::Unknown),
+        ] {
+            for &code in codes {
+                assert_eq!(condition(code), expected, "code {code}");
+            }
+        }
+    }
 }
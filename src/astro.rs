@@ -0,0 +1,347 @@
+//! Positions of the sun and the moon computed locally from coordinates and time,
+//! by the low-precision algorithms of Jean Meeus, "Astronomical Algorithms", as used by the NOAA solar calculator.
+//! Times are accurate to a minute or two between the polar circles.
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Altitudes of the sun center in degrees, the one of sunrise accounts for refraction and the solar disk radius.
+const SUNRISE: f64 = -0.833;
+const GOLDEN_HOUR: f64 = 6.0;
+const CIVIL_TWILIGHT: f64 = -6.0;
+const NAUTICAL_TWILIGHT: f64 = -12.0;
+const ASTRONOMICAL_TWILIGHT: f64 = -18.0;
+
+/// Mean length of the lunar cycle in days.
+const SYNODIC_MONTH: f64 = 29.530_588_853;
+
+/// Times when the sun rises above and sets below some altitude during the day.
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Crossing {
+    pub rising: DateTime<Utc>,
+    pub setting: DateTime<Utc>,
+}
+
+/// Sun events of a day, a crossing is `None` when the sun stays above or below its altitude all day,
+/// like during polar days and nights.
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Sun {
+    pub solar_noon: DateTime<Utc>,
+    pub day: Option<Crossing>,
+    /// Golden hour lasts from sunrise until the sun rises to 6°, and from when it goes down to 6° until sunset.
+    pub golden_hour: Option<Crossing>,
+    pub civil_twilight: Option<Crossing>,
+    pub nautical_twilight: Option<Crossing>,
+    pub astronomical_twilight: Option<Crossing>,
+    #[serde(with = "seconds")]
+    pub day_length: Duration,
+}
+
+impl Sun {
+    /// Sun events of the date at the location, the date is counted from midnight UTC.
+    pub fn on(date: NaiveDate, lat: f64, lon: f64) -> Self {
+        let midnight = Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN));
+        let noon = solar_noon(midnight, lon);
+        let day = crossing(midnight, noon, lat, lon, SUNRISE);
+        let day_length = match day {
+            Some(day) => day.setting - day.rising,
+            None if altitude(noon, lat, lon) > SUNRISE => Duration::days(1),
+            None => Duration::zero(),
+        };
+        Self {
+            solar_noon: noon,
+            day,
+            golden_hour: crossing(midnight, noon, lat, lon, GOLDEN_HOUR),
+            civil_twilight: crossing(midnight, noon, lat, lon, CIVIL_TWILIGHT),
+            nautical_twilight: crossing(midnight, noon, lat, lon, NAUTICAL_TWILIGHT),
+            astronomical_twilight: crossing(midnight, noon, lat, lon, ASTRONOMICAL_TWILIGHT),
+            day_length,
+        }
+    }
+}
+
+/// Whether the sun is above the horizon at the location.
+pub fn is_day(at: DateTime<Utc>, lat: f64, lon: f64) -> bool {
+    altitude(at, lat, lon) > SUNRISE
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    New,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    Full,
+    WaningGibbous,
+    LastQuarter,
+    WaningCrescent,
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq)]
+pub struct Moon {
+    pub phase: Phase,
+    /// Illuminated fraction of the disk from 0 to 1.
+    pub illumination: f64,
+    /// Days since the new moon.
+    pub age: f64,
+}
+
+impl Moon {
+    /// The moon as seen from the Earth, the same everywhere.
+    pub fn at(at: DateTime<Utc>) -> Self {
+        let t = julian_century(at);
+        let d = (297.850_192_1 + 445_267.111_403_4 * t).to_radians();
+        let m = (357.529_109_2 + 35_999.050_290_9 * t).to_radians();
+        let m_moon = (134.963_396_4 + 477_198.867_505_5 * t).to_radians();
+        // Phase angle, 0° at full moon.
+        let i = 180.0 - d.to_degrees() - 6.289 * m_moon.sin() + 2.100 * m.sin()
+            - 1.274 * (2.0 * d - m_moon).sin()
+            - 0.658 * (2.0 * d).sin()
+            - 0.214 * (2.0 * m_moon).sin()
+            - 0.110 * d.sin();
+        let illumination = (1.0 + i.to_radians().cos()) / 2.0;
+        // Fraction of the cycle, 0 at new moon and 0.5 at full moon.
+        let cycle = (180.0 - i).rem_euclid(360.0) / 360.0;
+        // Principal phases take an eighth of the cycle around their instant.
+        let phase = match (cycle * 8.0).round() as u8 {
+            1 => Phase::WaxingCrescent,
+            2 => Phase::FirstQuarter,
+            3 => Phase::WaxingGibbous,
+            4 => Phase::Full,
+            5 => Phase::WaningGibbous,
+            6 => Phase::LastQuarter,
+            7 => Phase::WaningCrescent,
+            _ => Phase::New,
+        };
+        Self {
+            phase,
+            illumination,
+            age: cycle * SYNODIC_MONTH,
+        }
+    }
+}
+
+fn solar_noon(midnight: DateTime<Utc>, lon: f64) -> DateTime<Utc> {
+    // Equation of time changes slowly, so it is taken at the approximate noon.
+    let approximate = midnight + minutes(720.0 - 4.0 * lon);
+    let (_, equation_of_time) = sun_position(approximate);
+    midnight + minutes(720.0 - 4.0 * lon - equation_of_time)
+}
+
+fn crossing(
+    midnight: DateTime<Utc>,
+    noon: DateTime<Utc>,
+    lat: f64,
+    lon: f64,
+    altitude: f64,
+) -> Option<Crossing> {
+    // The sun moves during the day, so its position is refined at the found time.
+    let time = |sign: f64| {
+        let mut at = noon;
+        for _ in 0..2 {
+            let (declination, equation_of_time) = sun_position(at);
+            let hour_angle = hour_angle(lat, declination, altitude)?;
+            at = midnight + minutes(720.0 - 4.0 * lon - equation_of_time + sign * 4.0 * hour_angle);
+        }
+        Some(at)
+    };
+    Some(Crossing {
+        rising: time(-1.0)?,
+        setting: time(1.0)?,
+    })
+}
+
+/// Hour angle in degrees when the sun is at the altitude, `None` if it never is.
+fn hour_angle(lat: f64, declination: f64, altitude: f64) -> Option<f64> {
+    let lat = lat.to_radians();
+    let declination = declination.to_radians();
+    let cos = (altitude.to_radians().sin() - lat.sin() * declination.sin())
+        / (lat.cos() * declination.cos());
+    (-1.0..=1.0).contains(&cos).then(|| cos.acos().to_degrees())
+}
+
+/// Altitude of the sun center in degrees, without refraction.
+fn altitude(at: DateTime<Utc>, lat: f64, lon: f64) -> f64 {
+    let (declination, equation_of_time) = sun_position(at);
+    let midnight = at.date_naive().and_time(NaiveTime::MIN).and_utc();
+    let utc_minutes = (at - midnight).num_milliseconds() as f64 / 60_000.0;
+    let hour_angle = ((utc_minutes + equation_of_time + 4.0 * lon) / 4.0 - 180.0).to_radians();
+    let lat = lat.to_radians();
+    let declination = declination.to_radians();
+    (lat.sin() * declination.sin() + lat.cos() * declination.cos() * hour_angle.cos())
+        .asin()
+        .to_degrees()
+}
+
+/// Declination of the sun in degrees and the equation of time in minutes.
+fn sun_position(at: DateTime<Utc>) -> (f64, f64) {
+    let t = julian_century(at);
+    let mean_longitude = (280.466_46 + t * (36_000.769_83 + t * 0.000_303_2)).rem_euclid(360.0);
+    let mean_anomaly = 357.529_11 + t * (35_999.050_29 - 0.000_153_7 * t);
+    let eccentricity = 0.016_708_634 - t * (0.000_042_037 + 0.000_000_126_7 * t);
+    let m = mean_anomaly.to_radians();
+    let center = m.sin() * (1.914_602 - t * (0.004_817 + 0.000_014 * t))
+        + (2.0 * m).sin() * (0.019_993 - 0.000_101 * t)
+        + (3.0 * m).sin() * 0.000_289;
+    let omega = (125.04 - 1934.136 * t).to_radians();
+    let apparent_longitude =
+        (mean_longitude + center - 0.005_69 - 0.004_78 * omega.sin()).to_radians();
+    let mean_obliquity =
+        23.0 + (26.0 + (21.448 - t * (46.815 + t * (0.000_59 - t * 0.001_813))) / 60.0) / 60.0;
+    let obliquity = (mean_obliquity + 0.002_56 * omega.cos()).to_radians();
+    let declination = (obliquity.sin() * apparent_longitude.sin())
+        .asin()
+        .to_degrees();
+
+    let y = (obliquity / 2.0).tan().powi(2);
+    let l = mean_longitude.to_radians();
+    let e = eccentricity;
+    let equation_of_time = y * (2.0 * l).sin() - 2.0 * e * m.sin()
+        + 4.0 * e * y * m.sin() * (2.0 * l).cos()
+        - 0.5 * y * y * (4.0 * l).sin()
+        - 1.25 * e * e * (2.0 * m).sin();
+    (declination, 4.0 * equation_of_time.to_degrees())
+}
+
+/// Julian centuries since the J2000.0 epoch.
+fn julian_century(at: DateTime<Utc>) -> f64 {
+    let julian_day = at.timestamp_millis() as f64 / 86_400_000.0 + 2_440_587.5;
+    (julian_day - 2_451_545.0) / 36_525.0
+}
+
+fn minutes(minutes: f64) -> Duration {
+    Duration::milliseconds((minutes * 60_000.0).round() as i64)
+}
+
+mod seconds {
+    use chrono::Duration;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(duration.num_seconds())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        i64::deserialize(deserializer).map(Duration::seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn assert_near(actual: DateTime<Utc>, expected: &str, case: &str) {
+        let difference = (actual - utc(expected)).num_seconds().abs();
+        assert!(
+            difference <= 120,
+            "{case}: expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn sun_events() {
+        // NOAA solar calculator, rounded to minutes.
+        let new_york = Sun::on(date("2024-06-21"), 40.7128, -74.006);
+        let day = new_york.day.unwrap();
+        assert_near(day.rising, "2024-06-21T09:25:00Z", "New York sunrise");
+        // Sunset after midnight UTC still belongs to the same day.
+        assert_near(day.setting, "2024-06-22T00:31:00Z", "New York sunset");
+        assert_near(
+            new_york.solar_noon,
+            "2024-06-21T16:58:00Z",
+            "New York solar noon",
+        );
+
+        // Refraction makes equinox days longer than nights even at the equator.
+        let equator = Sun::on(date("2024-03-20"), 0.0, 0.0);
+        let length = equator.day_length.num_seconds();
+        assert!((length - (12 * 60 + 7) * 60).abs() <= 120, "{length}");
+        let civil = equator.civil_twilight.unwrap();
+        let dawn = (equator.day.unwrap().rising - civil.rising).num_minutes();
+        assert!((20..=22).contains(&dawn), "{dawn}");
+
+        // Equation of time is at its extremes in February and November.
+        let greenwich = Sun::on(date("2024-02-11"), 51.4769, 0.0);
+        assert_near(
+            greenwich.solar_noon,
+            "2024-02-11T12:14:00Z",
+            "February solar noon",
+        );
+        let greenwich = Sun::on(date("2024-11-03"), 51.4769, 0.0);
+        assert_near(
+            greenwich.solar_noon,
+            "2024-11-03T11:44:00Z",
+            "November solar noon",
+        );
+    }
+
+    #[test]
+    fn events_are_ordered() {
+        let sun = Sun::on(date("2024-09-01"), 46.4825, 30.7233);
+        let crossings = [
+            sun.astronomical_twilight,
+            sun.nautical_twilight,
+            sun.civil_twilight,
+            sun.day,
+            sun.golden_hour,
+        ]
+        .map(Option::unwrap);
+        for pair in crossings.windows(2) {
+            assert!(pair[0].rising < pair[1].rising && pair[1].setting < pair[0].setting);
+        }
+        let golden_hour = sun.golden_hour.unwrap();
+        assert!(golden_hour.rising < sun.solar_noon && sun.solar_noon < golden_hour.setting);
+    }
+
+    #[test]
+    fn polar_day_and_night() {
+        let summer = Sun::on(date("2024-06-21"), 69.6492, 18.9553);
+        assert_eq!(summer.day, None);
+        assert_eq!(summer.day_length, Duration::days(1));
+        assert!(is_day(utc("2024-06-21T23:00:00Z"), 69.6492, 18.9553));
+
+        let winter = Sun::on(date("2024-12-21"), 69.6492, 18.9553);
+        assert_eq!(winter.day, None);
+        assert_eq!(winter.day_length, Duration::zero());
+        // Still some twilight around noon.
+        assert!(winter.civil_twilight.is_some());
+        assert!(!is_day(utc("2024-12-21T11:00:00Z"), 69.6492, 18.9553));
+    }
+
+    #[test]
+    fn day_and_night() {
+        // Kyiv at local noon and midnight in summer.
+        assert!(is_day(utc("2024-07-01T09:00:00Z"), 50.4501, 30.5234));
+        assert!(!is_day(utc("2024-07-01T21:00:00Z"), 50.4501, 30.5234));
+    }
+
+    #[test]
+    fn moon_phases() {
+        // Principal phases of January 2024 by the U.S. Naval Observatory.
+        for (at, phase, illumination) in [
+            ("2024-01-11T11:57:00Z", Phase::New, 0.0),
+            ("2024-01-18T03:53:00Z", Phase::FirstQuarter, 0.5),
+            ("2024-01-25T17:54:00Z", Phase::Full, 1.0),
+            ("2024-02-02T23:18:00Z", Phase::LastQuarter, 0.5),
+        ] {
+            let moon = Moon::at(utc(at));
+            assert_eq!(moon.phase, phase, "{at}");
+            assert!(
+                (moon.illumination - illumination).abs() < 0.02,
+                "{at}: expected {illumination}, got {}",
+                moon.illumination
+            );
+        }
+        let moon = Moon::at(utc("2024-01-14T12:00:00Z"));
+        assert_eq!(moon.phase, Phase::WaxingCrescent);
+        assert!((moon.age - 3.0).abs() < 0.5, "{}", moon.age);
+    }
+}
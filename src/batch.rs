@@ -0,0 +1,237 @@
+use std::io;
+
+use anyhow::Result;
+use proc_exit::Code;
+use serde::{Deserialize, Serialize};
+use uom::si::thermodynamic_temperature::degree_celsius;
+use uom::si::Unit;
+
+use weather::api::{self, AsyncApi};
+use weather::data::{Condition, Location, Provider};
+use weather::error::Error;
+use weather::storage::Storage;
+
+use crate::cli::OutputFormat;
+use crate::i18n::{format_number, tr, Message};
+
+/// Site as written in the batch file, resolved into a location without any prompts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Site {
+    pub query: String,
+    /// Known location or the given coordinates, otherwise the query is searched.
+    pub location: Option<Location>,
+}
+
+/// Result for a single site, either weather or the error that prevented getting it.
+#[derive(Serialize, Default, Debug)]
+pub struct Row {
+    pub site: String,
+    pub location: Option<String>,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    pub provider: Option<Provider>,
+    /// Temperature in degrees Celsius.
+    pub temperature: Option<f64>,
+    pub condition: Option<Condition>,
+    pub description: Option<String>,
+    /// Several locations were found, the top match is taken.
+    pub ambiguous: bool,
+    pub error: Option<String>,
+    #[serde(skip)]
+    pub code: Option<Code>,
+}
+
+/// Parse sites, one query, known location name or "lat,lon" per line.
+/// CSV files should have a "name" column, and can have "lat" and "lon" ones.
+pub fn parse(content: &str, csv: bool) -> Result<Vec<Site>, Error> {
+    if csv {
+        return parse_csv(content);
+    }
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| Site {
+            query: line.to_string(),
+            location: Location::from_coordinates(line),
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct CsvSite {
+    name: String,
+    #[serde(default)]
+    lat: Option<f64>,
+    #[serde(default)]
+    lon: Option<f64>,
+}
+
+fn parse_csv(content: &str) -> Result<Vec<Site>, Error> {
+    csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes())
+        .deserialize()
+        .map(|record| {
+            let record: CsvSite = record.map_err(|e| Error::InvalidImport(e.to_string()))?;
+            let location = match (record.lat, record.lon) {
+                (Some(lat), Some(lon)) => Some(Location {
+                    id: None,
+                    name: record.name.clone(),
+                    state: None,
+                    country: String::new(),
+                    lat: Some(lat),
+                    lon: Some(lon),
+                    timezone: None,
+                }),
+                _ => None,
+            };
+            Ok(Site {
+                query: record.name,
+                location,
+            })
+        })
+        .collect()
+}
+
+/// Use known locations instead of searching for them.
+pub fn resolve_known(storage: &Storage, sites: &mut [Site]) {
+    for site in sites.iter_mut().filter(|site| site.location.is_none()) {
+        site.location = storage.find_location(&site.query).cloned();
+    }
+}
+
+/// Get weather for all sites concurrently, at most `parallel` at a time, keeping their order.
+pub async fn fetch(apis: &[Box<dyn AsyncApi>], sites: &[Site], parallel: usize) -> Vec<Row> {
+    api::concurrently(sites, parallel, |site| async move {
+        let mut row = Row {
+            site: site.query.clone(),
+            ..Default::default()
+        };
+        if let Err(e) = fetch_site(apis, site, &mut row).await {
+            row.error = Some(e.to_string());
+            row.code = Some(e.exit_code());
+        }
+        row
+    })
+    .await
+}
+
+async fn fetch_site(apis: &[Box<dyn AsyncApi>], site: &Site, row: &mut Row) -> Result<(), Error> {
+    let location = match &site.location {
+        Some(location) => location.clone(),
+        None => {
+            let (mut locations, _) =
+                api::with_failover_async(apis, |api| api.search_location(&site.query)).await?;
+            // Nobody can choose here, so take the top match and let the user know.
+            row.ambiguous = locations.len() > 1;
+            if locations.is_empty() {
+                return Err(Error::LocationNotFound);
+            }
+            locations.swap_remove(0)
+        }
+    };
+    row.location = Some(location.to_string());
+    row.lat = location.lat;
+    row.lon = location.lon;
+
+    let (weather, provider) = api::with_failover_async(apis, |api| {
+        let location = &location;
+        async move {
+            let resolved = api.resolve_location(location).await?;
+            api.get_weather(&resolved).await
+        }
+    })
+    .await?;
+    row.provider = Some(provider);
+    row.temperature = Some(weather.temperature.get::<degree_celsius>());
+    row.condition = Some(weather.condition);
+    row.description = Some(weather.description);
+    Ok(())
+}
+
+pub fn show(rows: &[Row], format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Table => show_table(rows),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(rows)?),
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout());
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn show_table(rows: &[Row]) {
+    let header = [
+        tr(Message::Site),
+        tr(Message::Location),
+        tr(Message::Temperature),
+        tr(Message::Weather),
+        tr(Message::Note),
+    ]
+    .map(str::to_string);
+    let lines: Vec<[String; 5]> = rows
+        .iter()
+        .map(|row| {
+            let temperature = row
+                .temperature
+                .map(|t| format!("{}{}", format_number(t, 0), degree_celsius::abbreviation()));
+            let note = match (&row.error, row.ambiguous) {
+                (Some(error), _) => error.clone(),
+                (None, true) => tr(Message::AmbiguousLocation).to_string(),
+                (None, false) => String::new(),
+            };
+            [
+                row.site.clone(),
+                row.location.clone().unwrap_or_default(),
+                temperature.unwrap_or_default(),
+                row.description.clone().unwrap_or_default(),
+                note,
+            ]
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|cell| cell.chars().count());
+    for line in &lines {
+        for (width, cell) in widths.iter_mut().zip(line) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for line in [&header].into_iter().chain(&lines) {
+        let cells: Vec<_> = line
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sites() {
+        let sites = parse("Kyiv\n\n# depot\n 50.45, 30.52 \nOffice\n91,30\n", false).unwrap();
+        let queries: Vec<_> = sites.iter().map(|s| s.query.as_str()).collect();
+        assert_eq!(queries, ["Kyiv", "50.45, 30.52", "Office", "91,30"]);
+        assert_eq!(sites[0].location, None);
+        assert_eq!(sites[1].location.as_ref().unwrap().lat, Some(50.45));
+        // Out of range coordinates are searched as is.
+        assert_eq!(sites[3].location, None);
+
+        let sites = parse("name,lat,lon\nDepot,50.0,30.0\nLviv,,\n", true).unwrap();
+        assert_eq!(sites[0].location.as_ref().unwrap().lon, Some(30.0));
+        assert_eq!(sites[1].query, "Lviv");
+        assert_eq!(sites[1].location, None);
+
+        let sites = parse("name\nKyiv\n", true).unwrap();
+        assert_eq!(sites[0].query, "Kyiv");
+        assert!(parse("city\nKyiv\n", true).is_err());
+    }
+}
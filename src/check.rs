@@ -0,0 +1,122 @@
+use anyhow::Result;
+use proc_exit::{sysexits, Code};
+
+use weather::alert::{self, Alert, Event, State};
+use weather::api::{self, AsyncApi, Client};
+use weather::data::Location;
+use weather::storage::Storage;
+
+use crate::block_on;
+use crate::daemon::Cache;
+use crate::i18n::{tr, Message};
+use crate::ui::{println, with_spinner, wprintln};
+
+/// Exit code when any alert is triggered, see "Exit codes" in README.
+pub const TRIGGERED: Code = Code::new(3);
+
+/// Known location the alert applies to, or the saved one.
+pub fn alert_location<'a>(storage: &'a Storage, alert: &Alert) -> Option<&'a Location> {
+    match alert.rule.location() {
+        Some(name) => storage.find_location(name),
+        None => storage.get_shared_location(),
+    }
+}
+
+/// Evaluate all alerts, notifying about the ones whose state has changed since the last check.
+/// Weather the daemon keeps fresh is taken from its cache instead of providers.
+/// Return the exit code: of the first failure if any, otherwise whether any alert is triggered.
+pub fn check(
+    storage: &Storage,
+    apis: &[Box<dyn AsyncApi>],
+    cache: &Cache,
+    client: &Client,
+    state: &mut State,
+    parallel: usize,
+) -> Result<Code> {
+    let alerts = storage.get_alerts();
+    // Every location is fetched once, even if several alerts apply to it.
+    let mut locations: Vec<Location> = Vec::new();
+    let targets: Vec<_> = alerts
+        .iter()
+        .map(|alert| {
+            let location = alert_location(storage, alert)?;
+            let index = match locations.iter().position(|l| l == location) {
+                Some(index) => index,
+                None => {
+                    locations.push(location.clone());
+                    locations.len() - 1
+                }
+            };
+            Some(index)
+        })
+        .collect();
+
+    let results = with_spinner(|| {
+        block_on(api::concurrently(
+            &locations,
+            parallel,
+            |location| async move {
+                if let Some(answer) = cache.get(location) {
+                    return Ok(answer);
+                }
+                api::with_failover_async(apis, move |api| async move {
+                    let resolved = api.resolve_location(location).await?;
+                    api.get_weather(&resolved).await
+                })
+                .await
+            },
+        ))
+    })?;
+
+    let mut failure = None;
+    let mut triggered_any = false;
+    for (alert, target) in alerts.iter().zip(targets) {
+        let Some(index) = target else {
+            let message = match alert.rule.location() {
+                Some(name) => format!("{}: \"{name}\"", tr(Message::KnownLocationNotFound)),
+                None => tr(Message::NoSavedLocation).to_string(),
+            };
+            wprintln(&format!("{}: {message}", alert.name));
+            failure.get_or_insert(sysexits::USAGE_ERR);
+            continue;
+        };
+        let (weather, provider) = match &results[index] {
+            Ok(answer) => answer,
+            Err(e) => {
+                wprintln(&format!("{}: {e}", alert.name));
+                failure.get_or_insert(e.exit_code());
+                continue;
+            }
+        };
+
+        let triggered = alert.rule.matches(weather);
+        triggered_any |= triggered;
+        let status = if triggered {
+            tr(Message::AlertTriggered)
+        } else {
+            tr(Message::AlertNotTriggered)
+        };
+        println(&format!("{} ({}): {status}", alert.name, alert.rule));
+
+        if state.is_triggered(&alert.name) != triggered {
+            let event = Event::new(alert, triggered, &locations[index], *provider, weather);
+            match alert::notify(client, alert, &event) {
+                Ok(()) => {
+                    state.update(&alert.name, triggered);
+                }
+                // Keep the old state, so notifications are tried again next time.
+                Err(e) => {
+                    wprintln(&e.to_string());
+                    failure.get_or_insert(e.exit_code());
+                }
+            }
+        }
+    }
+    state.retain(alerts);
+
+    Ok(match failure {
+        Some(code) => code,
+        None if triggered_any => TRIGGERED,
+        None => Code::SUCCESS,
+    })
+}
@@ -1,8 +1,13 @@
-use std::path::PathBuf;
+#[cfg(feature = "server")]
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 
-use clap::{Parser, Subcommand};
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use weather::api::TlsVersion;
+use weather::data::{Language, Provider};
 
-use crate::data::Provider;
+use crate::ui::IconSet;
 
 pub mod prelude {
     pub use clap::Parser;
@@ -12,6 +17,10 @@ pub mod prelude {
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
+    /// Named profile with its own config, the default one is used if not given.
+    #[arg(long, global = true, env = "WEATHER_CLI_PROFILE")]
+    pub profile: Option<String>,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -27,6 +36,23 @@ pub enum Command {
         /// Path to config file.
         #[arg(short, long)]
         config: Option<PathBuf>,
+
+        #[command(flatten)]
+        http: HttpArgs,
+    },
+    /// Remove credentials of the provider.
+    Unconfigure {
+        /// Specific weather API provider.
+        #[arg(value_enum)]
+        provider: Provider,
+
+        /// Do not ask for confirmation.
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
     },
     /// Show weather by location.
     Get {
@@ -34,11 +60,427 @@ pub enum Command {
         #[arg(short, long)]
         provider: Option<Provider>,
 
-        /// Choose a location (city, town, or village) and save the choice per provider.
+        /// Choose a location (city, town, or village) and save the choice for all providers,
+        /// several ones are shown at once without saving.
+        locations: Vec<String>,
+
+        /// Get weather for sites from the file without prompts, one query, known location
+        /// or "lat,lon" per line, or a CSV with a "name" column.
+        #[arg(long, conflicts_with = "locations")]
+        batch: Option<PathBuf>,
+
+        /// Output format of the batch results.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table, requires = "batch")]
+        format: OutputFormat,
+
+        /// Choose a language of output and save the choice.
+        #[arg(short, long)]
+        lang: Option<Language>,
+
+        /// Prefix weather conditions with icons.
+        #[arg(long, value_enum, default_value_t = IconSet::None)]
+        icons: IconSet,
+
+        /// Show weather conditions as ASCII art.
+        #[arg(long)]
+        art: bool,
+
+        /// Also show sunrise, sunset, twilight and moon phase.
+        #[arg(long, conflicts_with = "batch")]
+        astro: bool,
+
+        /// Time zone to show times in.
+        #[arg(long, value_enum, default_value_t = TimeZoneChoice::Local)]
+        tz: TimeZoneChoice,
+
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        #[command(flatten)]
+        http: HttpArgs,
+    },
+    /// Show sunrise, sunset, twilight and moon phase, computed locally without calling providers.
+    Astro {
+        /// Known location, "lat,lon" or a query to search, the saved location is used if not given.
         location: Option<String>,
 
+        /// Date to show, today if not given.
+        #[arg(long)]
+        date: Option<NaiveDate>,
+
+        /// Time zone to show times in.
+        #[arg(long, value_enum, default_value_t = TimeZoneChoice::Local)]
+        tz: TimeZoneChoice,
+
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        #[command(flatten)]
+        http: HttpArgs,
+    },
+    /// Serve weather as a JSON API for local clients, so they do not need provider keys.
+    #[cfg(feature = "server")]
+    Serve {
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: SocketAddr,
+
+        /// Time to keep weather and search results, in seconds.
+        #[arg(long, default_value_t = 600)]
+        cache_ttl: u64,
+
+        /// Maximum number of requests per minute from a single client, 0 means unlimited.
+        #[arg(long, default_value_t = 60)]
+        rate_limit: u32,
+
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        #[command(flatten)]
+        http: HttpArgs,
+    },
+    /// Poll weather of known and saved locations and serve it as Prometheus metrics.
+    #[cfg(feature = "server")]
+    Exporter {
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1:9100")]
+        bind: SocketAddr,
+
+        /// Time between polls, in seconds.
+        #[arg(long, default_value_t = 300, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+
         /// Path to config file.
         #[arg(short, long)]
         config: Option<PathBuf>,
+
+        #[command(flatten)]
+        http: HttpArgs,
     },
+    /// Check alert rules from the config and notify about changes, exiting with 3 if any is triggered.
+    Check {
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        #[command(flatten)]
+        http: HttpArgs,
+    },
+    /// Show calls made to providers and remaining budget.
+    Quota {
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Export config and known locations to a portable file.
+    Export {
+        /// File to write, standard output is used if not given.
+        file: Option<PathBuf>,
+
+        /// Include API keys and proxy credentials.
+        #[arg(long)]
+        include_secrets: bool,
+
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Import config or known locations from a file, keeping current values on conflicts.
+    Import {
+        /// Exported config, or locations in CSV or GeoJSON.
+        file: PathBuf,
+
+        /// Format of the file, guessed from its extension if not given.
+        #[arg(long, value_enum)]
+        format: Option<ImportFormat>,
+
+        /// Replace current values on conflicts.
+        #[arg(long)]
+        overwrite: bool,
+
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Manage saved locations.
+    Locations {
+        #[command(subcommand)]
+        command: LocationsCommand,
+    },
+    /// Manage named profiles.
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+    /// Inspect and manage the config file.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Inspect configured providers.
+    Provider {
+        #[command(subcommand)]
+        command: ProviderCommand,
+    },
+    /// Fetch weather on schedule and check alerts in a long-running process.
+    Daemon {
+        #[command(subcommand)]
+        command: DaemonCommand,
+    },
+}
+
+impl Command {
+    /// Whether the command uses the config of the profile, i.e. it is not a profile command itself
+    /// and no explicit config path overrides the profile.
+    pub fn uses_profile(&self) -> bool {
+        let config = match self {
+            Command::Profile { .. } => return false,
+            Command::Configure { config, .. }
+            | Command::Unconfigure { config, .. }
+            | Command::Get { config, .. }
+            | Command::Astro { config, .. }
+            | Command::Check { config, .. }
+            | Command::Quota { config, .. }
+            | Command::Export { config, .. }
+            | Command::Import { config, .. } => config,
+            #[cfg(feature = "server")]
+            Command::Serve { config, .. } | Command::Exporter { config, .. } => config,
+            Command::Locations { command } => match command {
+                LocationsCommand::List { config } | LocationsCommand::Clear { config, .. } => {
+                    config
+                }
+            },
+            Command::Config { command } => match command {
+                ConfigCommand::Path { config }
+                | ConfigCommand::Show { config, .. }
+                | ConfigCommand::Edit { config }
+                | ConfigCommand::Validate { config }
+                | ConfigCommand::Reset { config, .. } => config,
+            },
+            Command::Provider { command } => match command {
+                ProviderCommand::List { config, .. } => config,
+            },
+            Command::Daemon { command } => match command {
+                DaemonCommand::Run { config, .. }
+                | DaemonCommand::Status { config }
+                | DaemonCommand::Stop { config } => config,
+            },
+        };
+        config.is_none()
+    }
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// Aligned columns for reading.
+    Table,
+    /// Array of objects.
+    Json,
+    /// Comma-separated values with a header.
+    Csv,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+pub enum TimeZoneChoice {
+    /// Time zone of this computer.
+    Local,
+    /// Time zone of the location, the local one is used if it is unknown.
+    Site,
+    /// Coordinated Universal Time.
+    #[value(name = "UTC", alias = "utc")]
+    Utc,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+pub enum ImportFormat {
+    /// File produced by the export command.
+    Export,
+    /// Locations with "name", "lat" and "lon" columns, "state" and "country" are optional.
+    Csv,
+    /// Feature collection of points with a "name" property.
+    Geojson,
+}
+
+impl ImportFormat {
+    pub fn guess(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("csv") => ImportFormat::Csv,
+            Some(e) if e.eq_ignore_ascii_case("geojson") || e.eq_ignore_ascii_case("json") => {
+                ImportFormat::Geojson
+            }
+            _ => ImportFormat::Export,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LocationsCommand {
+    /// List known locations that can be chosen by name.
+    List {
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Forget the saved location and known locations.
+    Clear {
+        /// Do not ask for confirmation.
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ProfileCommand {
+    /// List existing profiles.
+    List,
+    /// Create a profile with the default config.
+    Create {
+        /// Name of the profile.
+        name: String,
+    },
+    /// Create a profile with the config of another one.
+    Copy {
+        /// Name of the profile to copy.
+        from: String,
+
+        /// Name of the new profile.
+        to: String,
+    },
+    /// Delete a profile with its config.
+    Delete {
+        /// Name of the profile.
+        name: String,
+
+        /// Do not ask for confirmation.
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print path to the config file.
+    Path {
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Print the config.
+    Show {
+        /// Show API keys and proxy credentials instead of hiding them.
+        #[arg(long)]
+        show_secrets: bool,
+
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Open the config in $EDITOR and validate it before saving.
+    Edit {
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Check the config for mistakes.
+    Validate {
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Replace the config with the default one.
+    Reset {
+        /// Do not ask for confirmation.
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ProviderCommand {
+    /// List configured providers with validity of their API keys.
+    List {
+        /// Do not check API keys with providers.
+        #[arg(long)]
+        no_check: bool,
+
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        #[command(flatten)]
+        http: HttpArgs,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DaemonCommand {
+    /// Run in the foreground until stopped, logging JSON lines to standard error.
+    Run {
+        /// Seconds between fetches of every location, instead of the one from the config.
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: Option<u64>,
+
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        #[command(flatten)]
+        http: HttpArgs,
+    },
+    /// Show whether the daemon is running and the latest weather it got.
+    Status {
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Stop the running daemon.
+    Stop {
+        /// Path to config file.
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+}
+
+/// Overrides for the HTTP settings from the config.
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Network options")]
+pub struct HttpArgs {
+    /// Timeout for provider API requests in seconds.
+    #[arg(long)]
+    pub timeout: Option<u64>,
+
+    /// Timeout for every read of the response in seconds.
+    #[arg(long)]
+    pub read_timeout: Option<u64>,
+
+    /// Proxy for provider API requests, can contain credentials.
+    #[arg(long)]
+    pub proxy: Option<String>,
+
+    /// Comma-separated list of hosts that should not be proxied.
+    #[arg(long)]
+    pub no_proxy: Option<String>,
+
+    /// PEM file with extra root certificates to trust.
+    #[arg(long)]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Minimum TLS version.
+    #[arg(long)]
+    pub min_tls_version: Option<TlsVersion>,
+
+    /// Maximum number of concurrent requests to provider APIs.
+    #[arg(long)]
+    pub parallel: Option<usize>,
 }
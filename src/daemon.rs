@@ -0,0 +1,421 @@
+use std::fs;
+use std::io::{self, ErrorKind, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::process;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use proc_exit::Code;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tempfile::NamedTempFile;
+use uom::si::thermodynamic_temperature::degree_celsius;
+use uom::si::Unit;
+
+use weather::alert::{self, Alert, Event, State};
+use weather::api::{self, AsyncApi, Client};
+use weather::data::{Location, Provider, Weather};
+use weather::storage::{DaemonSettings, Storage};
+
+use crate::i18n::{format_number, tr, Message};
+use crate::ui::{eprintln, println, sprintln};
+
+use desktop::Desktop;
+
+mod desktop;
+
+/// State of the running daemon along with the latest weather of every location.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Status {
+    pub pid: u32,
+    /// Unix time of the start.
+    pub started: u64,
+    pub locations: Vec<Entry>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Entry {
+    pub location: Location,
+    /// Unix time of the last successful fetch.
+    pub fetched: Option<u64>,
+    pub provider: Option<Provider>,
+    /// Weather of the last successful fetch, kept when the next one fails.
+    pub weather: Option<Weather>,
+    /// Error of the last fetch, if it failed.
+    pub error: Option<String>,
+    /// Unix time of the next fetch.
+    pub next: u64,
+}
+
+/// Latest weather fetched by the daemon, so other commands do not ask providers meanwhile.
+pub struct Cache(Option<Status>);
+
+impl Cache {
+    /// Read the state file of the daemon, if any.
+    pub fn load(path: &Path) -> Self {
+        Cache(read_status(path).unwrap_or_else(|e| {
+            warn!("{e:#}");
+            None
+        }))
+    }
+
+    /// Weather of the location along with the provider, if its last fetch has succeeded
+    /// and the next one is not due yet, i.e. the weather is fresher than the interval.
+    pub fn get(&self, location: &Location) -> Option<(Weather, Provider)> {
+        let entry = self.0.as_ref()?.locations.iter().find(|entry| {
+            entry.location.name == location.name
+                && entry.location.lat == location.lat
+                && entry.location.lon == location.lon
+        })?;
+        if entry.error.is_some() || entry.next <= now() {
+            return None;
+        }
+        Some((entry.weather.clone()?, entry.provider?))
+    }
+}
+
+/// State file of the daemon next to the config.
+pub fn state_path(config: Option<&PathBuf>) -> Result<PathBuf> {
+    Ok(Storage::path(config)?.with_extension("daemon.json"))
+}
+
+/// Long-running process fetching weather on schedule and checking alerts on every fetch.
+pub struct Daemon {
+    pub apis: Vec<Box<dyn AsyncApi>>,
+    /// Blocking client for webhooks, created outside of the runtime.
+    pub webhooks: Arc<Client>,
+    pub locations: Vec<Location>,
+    /// Alerts along with locations they apply to.
+    pub alerts: Vec<(Alert, Location)>,
+    pub schedule: DaemonSettings,
+    pub parallel: usize,
+    /// State file of the daemon.
+    pub path: PathBuf,
+    /// States of alerts, shared with the `check` command.
+    pub alerts_path: PathBuf,
+}
+
+impl Daemon {
+    /// Run until SIGTERM or Ctrl-C, the state file is removed on exit.
+    pub async fn run(self) -> Result<()> {
+        if let Some(status) = read_status(&self.path)? {
+            if is_running(status.pid) {
+                eprintln(
+                    &format!("{}: {}", tr(Message::DaemonAlreadyRunning), status.pid),
+                    Code::FAILURE,
+                )
+            }
+        }
+        let mut status = Status {
+            pid: process::id(),
+            started: now(),
+            locations: self
+                .locations
+                .iter()
+                .map(|location| Entry {
+                    location: location.clone(),
+                    fetched: None,
+                    provider: None,
+                    weather: None,
+                    error: None,
+                    next: 0,
+                })
+                .collect(),
+        };
+        write_status(&self.path, &status)?;
+        info!(pid = status.pid, locations = status.locations.len(); "daemon started");
+
+        let desktop = Desktop::connect().await;
+        let mut states = State::load(&self.alerts_path)?;
+        let mut shutdown = pin!(shutdown());
+        loop {
+            let due: Vec<_> = (0..status.locations.len())
+                .filter(|&i| status.locations[i].next <= now())
+                .collect();
+            let results = api::concurrently(due.iter().copied(), self.parallel, |i| {
+                let location = &self.locations[i];
+                api::with_failover_async(&self.apis, move |api| async move {
+                    let resolved = api.resolve_location(location).await?;
+                    api.get_weather(&resolved).await
+                })
+            })
+            .await;
+
+            for (i, result) in iter::zip(due, results) {
+                let entry = &mut status.locations[i];
+                let label = entry.location.to_string();
+                entry.next = now() + self.schedule.interval_of(&entry.location);
+                match result {
+                    Ok((weather, provider)) => {
+                        info!(
+                            location = label,
+                            provider = format!("{provider:?}"),
+                            temperature = weather.temperature.get::<degree_celsius>();
+                            "fetched weather"
+                        );
+                        self.check_alerts(&desktop, &mut states, entry, &weather, provider)
+                            .await;
+                        entry.fetched = Some(now());
+                        entry.provider = Some(provider);
+                        entry.weather = Some(weather);
+                        entry.error = None;
+                    }
+                    Err(e) => {
+                        warn!(location = label, error = e.to_string(); "failed to fetch weather");
+                        entry.error = Some(e.to_string());
+                    }
+                }
+            }
+            write_status(&self.path, &status)?;
+            states.store(&self.alerts_path)?;
+
+            let next = status.locations.iter().map(|entry| entry.next).min();
+            let delay = next.unwrap_or_default().saturating_sub(now());
+            if tokio::time::timeout(Duration::from_secs(delay), &mut shutdown)
+                .await
+                .is_ok()
+            {
+                break;
+            }
+        }
+
+        info!(pid = status.pid; "daemon stopped");
+        fs::remove_file(&self.path).context("Failed to remove the state file")
+    }
+
+    /// Notify about alerts of the location whose state has changed.
+    async fn check_alerts(
+        &self,
+        desktop: &Desktop,
+        states: &mut State,
+        entry: &Entry,
+        weather: &Weather,
+        provider: Provider,
+    ) {
+        let location = &entry.location;
+        let alerts = self.alerts.iter().filter(|(_, target)| target == location);
+        for (alert, _) in alerts {
+            let triggered = alert.rule.matches(weather);
+            // Each way is retried on its own, so one that works is not repeated for the other.
+            let notify = states.is_triggered(&alert.name) != triggered;
+            let show = states.is_shown(&alert.name) != triggered;
+            if !notify && !show {
+                continue;
+            }
+            info!(
+                alert = alert.name,
+                location = location.to_string(),
+                triggered = triggered;
+                "alert state changed"
+            );
+            if show {
+                let status = if triggered {
+                    tr(Message::AlertTriggered)
+                } else {
+                    tr(Message::AlertNotTriggered)
+                };
+                let temperature = weather.temperature.get::<degree_celsius>();
+                desktop
+                    .notify(
+                        &format!("{}: {status}", alert.name),
+                        &format!(
+                            "{location}: {}{}, {}",
+                            format_number(temperature, 0),
+                            degree_celsius::abbreviation(),
+                            weather.description
+                        ),
+                    )
+                    .await;
+                states.update_shown(&alert.name, triggered);
+            }
+            if !notify {
+                continue;
+            }
+
+            // Notifications can block, e.g. on shell commands or slow webhooks.
+            let event = Event::new(alert, triggered, location, provider, weather);
+            let (client, owned) = (self.webhooks.clone(), alert.clone());
+            let result =
+                tokio::task::spawn_blocking(move || alert::notify(&client, &owned, &event)).await;
+            match result {
+                Ok(Ok(())) => {
+                    states.update(&alert.name, triggered);
+                }
+                // Keep the old state, so notifications are tried again on the next fetch.
+                Ok(Err(e)) => warn!(alert = alert.name; "{e}"),
+                Err(e) => warn!(alert = alert.name; "notification has panicked: {e}"),
+            }
+        }
+    }
+}
+
+/// Show whether the daemon is running and the latest weather it got.
+pub fn status(path: &Path) -> Result<()> {
+    let Some(status) = read_status(path)?.filter(|status| is_running(status.pid)) else {
+        eprintln(tr(Message::DaemonNotRunning), Code::FAILURE)
+    };
+    let now = now();
+    sprintln(&format!(
+        "{}: {}, {} {}",
+        tr(Message::DaemonRunning),
+        status.pid,
+        tr(Message::Uptime),
+        format_age(now.saturating_sub(status.started))
+    ));
+    for entry in &status.locations {
+        let weather = match (&entry.weather, entry.fetched) {
+            (Some(weather), Some(fetched)) => format!(
+                "{}{}, {} ({} {})",
+                format_number(weather.temperature.get::<degree_celsius>(), 0),
+                degree_celsius::abbreviation(),
+                weather.description,
+                format_age(now.saturating_sub(fetched)),
+                tr(Message::Ago)
+            ),
+            _ => tr(Message::NotFetched).to_string(),
+        };
+        println(&format!("{}: {weather}", entry.location));
+        if let Some(error) = &entry.error {
+            println(&format!("  {error}"));
+        }
+    }
+    Ok(())
+}
+
+/// Ask the running daemon to stop and wait for it.
+pub fn stop(path: &Path) -> Result<()> {
+    let Some(status) = read_status(path)?.filter(|status| is_running(status.pid)) else {
+        eprintln(tr(Message::DaemonNotRunning), Code::FAILURE)
+    };
+    terminate(status.pid).context("Failed to stop the daemon")?;
+    for _ in 0..100 {
+        if !is_running(status.pid) || !path.exists() {
+            sprintln(tr(Message::DaemonStopped));
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    anyhow::bail!("Daemon with PID {} has not stopped in time", status.pid)
+}
+
+/// Log records as JSON lines with their key-values, so they can be collected by log shippers.
+pub fn init_logger() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+        .format(|buf, record| {
+            let mut line = Map::new();
+            line.insert("time".into(), buf.timestamp_millis().to_string().into());
+            line.insert("level".into(), record.level().as_str().into());
+            line.insert("target".into(), record.target().into());
+            line.insert("message".into(), record.args().to_string().into());
+            let _ = record.key_values().visit(&mut Fields(&mut line));
+            writeln!(buf, "{}", Value::Object(line))
+        })
+        .init();
+}
+
+struct Fields<'a>(&'a mut Map<String, Value>);
+
+impl<'kvs> log::kv::Visitor<'kvs> for Fields<'_> {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        let value = serde_json::to_value(value).unwrap_or_else(|e| e.to_string().into());
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+fn read_status(path: &Path) -> Result<Option<Status>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(
+            serde_json::from_str(&content).context("State file of the daemon is corrupted")?,
+        )),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).context("Failed to read the state file of the daemon"),
+    }
+}
+
+/// Replace the state file atomically, so readers never see a partial one.
+fn write_status(path: &Path, status: &Status) -> Result<()> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let mut file = NamedTempFile::new_in(dir.unwrap_or(Path::new(".")))?;
+    serde_json::to_writer_pretty(&mut file, status)?;
+    file.persist(path)?;
+    Ok(())
+}
+
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{seconds} {}", tr(Message::Seconds)),
+        60..=3599 => format!("{} {}", seconds / 60, tr(Message::Minutes)),
+        _ => format!("{} {}", seconds / 3600, tr(Message::Hours)),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Wait for SIGTERM, e.g. from `daemon stop`, or Ctrl-C.
+async fn shutdown() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                let ctrl_c = pin!(tokio::signal::ctrl_c());
+                futures_util::future::select(pin!(terminate.recv()), ctrl_c).await;
+                return;
+            }
+            Err(e) => warn!("failed to listen for SIGTERM: {e}"),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    use nix::errno::Errno;
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+
+    // No signal is sent, only the existence of the process is checked.
+    let Ok(pid) = i32::try_from(pid) else {
+        return false;
+    };
+    !matches!(kill(Pid::from_raw(pid), None), Err(Errno::ESRCH))
+}
+
+#[cfg(not(unix))]
+fn is_running(_: u32) -> bool {
+    // Trust the state file, it is removed on exit.
+    true
+}
+
+#[cfg(unix)]
+fn terminate(pid: u32) -> io::Result<()> {
+    use nix::sys::signal::{kill, Signal};
+    use nix::unistd::Pid;
+
+    let pid = i32::try_from(pid).map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+    kill(Pid::from_raw(pid), Signal::SIGTERM).map_err(io::Error::from)
+}
+
+#[cfg(not(unix))]
+fn terminate(_: u32) -> io::Result<()> {
+    Err(io::Error::new(
+        ErrorKind::Unsupported,
+        "stopping the daemon is supported only on Unix, stop its process instead",
+    ))
+}
@@ -0,0 +1,60 @@
+#[cfg(feature = "dbus")]
+use std::collections::HashMap;
+
+#[cfg(feature = "dbus")]
+use log::warn;
+use log::{debug, info};
+
+/// Freedesktop notifications, or nothing where there is no session bus, e.g. on servers.
+pub enum Desktop {
+    #[cfg(feature = "dbus")]
+    Bus(zbus::Connection),
+    Noop,
+}
+
+impl Desktop {
+    pub async fn connect() -> Self {
+        #[cfg(feature = "dbus")]
+        match zbus::Connection::session().await {
+            Ok(connection) => return Self::Bus(connection),
+            Err(e) => info!("desktop notifications are disabled, no session bus: {e}"),
+        }
+        #[cfg(not(feature = "dbus"))]
+        info!("desktop notifications are disabled, built without D-Bus support");
+        Self::Noop
+    }
+
+    /// Show the notification, failures are only logged as alerts have other ways to notify.
+    pub async fn notify(&self, summary: &str, body: &str) {
+        match self {
+            #[cfg(feature = "dbus")]
+            Self::Bus(connection) => {
+                // See the Desktop Notifications Specification for arguments of "Notify".
+                let hints: HashMap<&str, zbus::zvariant::Value> = HashMap::new();
+                let arguments = (
+                    env!("CARGO_PKG_NAME"),
+                    0u32,
+                    "",
+                    summary,
+                    body,
+                    Vec::<&str>::new(),
+                    hints,
+                    -1i32,
+                );
+                let result = connection
+                    .call_method(
+                        Some("org.freedesktop.Notifications"),
+                        "/org/freedesktop/Notifications",
+                        Some("org.freedesktop.Notifications"),
+                        "Notify",
+                        &arguments,
+                    )
+                    .await;
+                if let Err(e) = result {
+                    warn!("failed to show desktop notification: {e}");
+                }
+            }
+            Self::Noop => debug!("skipped desktop notification \"{summary}\": {body}"),
+        }
+    }
+}
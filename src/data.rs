@@ -1,22 +1,143 @@
 use std::fmt::{Display, Formatter};
 
+use chrono::{DateTime, FixedOffset, NaiveDate, Offset, TimeZone as _, Utc};
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
-use uom::si::f64::ThermodynamicTemperature;
+use uom::si::f64::{Pressure, Ratio, ThermodynamicTemperature, Velocity};
 
-#[derive(Deserialize, Serialize, ValueEnum, Copy, Clone, Debug, PartialEq)]
+pub mod derived;
+
+#[derive(Deserialize, Serialize, ValueEnum, Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Provider {
     OpenWeather,
     WeatherApi,
     AccuWeather,
 }
 
+#[derive(Deserialize, Serialize, ValueEnum, Copy, Clone, Debug, PartialEq)]
+pub enum Language {
+    /// English.
+    En,
+    /// Ukrainian.
+    Uk,
+}
+
+impl Language {
+    /// ISO 639-1 language code.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::Uk => "uk",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct Weather {
     pub temperature: ThermodynamicTemperature,
+    pub condition: Condition,
+    pub is_day: bool,
     pub description: String,
+    /// Relative humidity, if reported by the provider.
+    pub humidity: Option<Ratio>,
+    /// Atmospheric pressure, if reported by the provider.
+    pub pressure: Option<Pressure>,
+    /// Wind speed, if reported by the provider.
+    pub wind_speed: Option<Velocity>,
+    /// Apparent temperature, if reported by the provider, see [`derived`] otherwise.
+    pub feels_like: Option<ThermodynamicTemperature>,
+    /// Dew point, if reported by the provider, see [`derived`] otherwise.
+    pub dew_point: Option<ThermodynamicTemperature>,
+    /// When the weather was observed, if reported by the provider.
+    pub observed: Option<DateTime<Utc>>,
+    /// Time zone of the location, if reported by the provider.
+    pub timezone: Option<TimeZone>,
+}
+
+/// Time zone of a location, kept as its name or as an offset like "+03:00".
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub enum TimeZone {
+    /// IANA name like "Europe/Kyiv", which accounts for daylight saving time.
+    Named(String),
+    /// Offset from UTC in seconds, only valid around the observation time.
+    Offset(i32),
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+impl TimeZone {
+    /// Offset from UTC at the given time, `None` if the name is not in the time zone database.
+    pub fn offset_at(&self, at: DateTime<Utc>) -> Option<FixedOffset> {
+        match self {
+            TimeZone::Named(name) => {
+                let tz: chrono_tz::Tz = name.parse().ok()?;
+                Some(tz.offset_from_utc_datetime(&at.naive_utc()).fix())
+            }
+            TimeZone::Offset(seconds) => FixedOffset::east_opt(*seconds),
+        }
+    }
+}
+
+impl TryFrom<String> for TimeZone {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        if !text.starts_with(['+', '-']) {
+            return Ok(TimeZone::Named(text));
+        }
+        let offset: FixedOffset = text
+            .parse()
+            .map_err(|_| format!("time zone offset \"{text}\" is invalid"))?;
+        Ok(TimeZone::Offset(offset.local_minus_utc()))
+    }
+}
+
+impl From<TimeZone> for String {
+    fn from(value: TimeZone) -> Self {
+        match value {
+            TimeZone::Named(name) => name,
+            TimeZone::Offset(seconds) => FixedOffset::east_opt(seconds)
+                .map_or_else(|| seconds.to_string(), |offset| offset.to_string()),
+        }
+    }
+}
+
+/// Expected weather of a single day, in the local date of the location.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct Forecast {
+    pub date: NaiveDate,
+    pub min_temperature: ThermodynamicTemperature,
+    pub max_temperature: ThermodynamicTemperature,
+    pub condition: Condition,
+    pub description: String,
+}
+
+/// Provider-independent weather condition.
+///
+/// Every provider has its own set of condition codes, so they are normalized into this one.
+/// The original provider description is still kept in `Weather` for display purposes.
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Condition {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Overcast,
+    Mist,
+    Fog,
+    Haze,
+    Drizzle,
+    Rain,
+    HeavyRain,
+    FreezingRain,
+    Thunderstorm,
+    Snow,
+    HeavySnow,
+    Sleet,
+    Hail,
+    Windy,
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct Location {
     pub id: Option<String>,
     pub name: String,
@@ -24,14 +145,74 @@ pub struct Location {
     pub country: String,
     pub lat: Option<f64>,
     pub lon: Option<f64>,
+    /// Time zone, if reported by the provider along with the location or its weather.
+    pub timezone: Option<TimeZone>,
+}
+
+impl Location {
+    /// Parse "lat,lon" if both are numbers in a valid range, the text itself becomes the name.
+    pub fn from_coordinates(text: &str) -> Option<Self> {
+        let (lat, lon) = text.split_once(',')?;
+        let lat: f64 = lat.trim().parse().ok()?;
+        let lon: f64 = lon.trim().parse().ok()?;
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            return None;
+        }
+        Some(Self {
+            id: None,
+            name: text.trim().to_string(),
+            state: None,
+            country: String::new(),
+            lat: Some(lat),
+            lon: Some(lon),
+            timezone: None,
+        })
+    }
 }
 
 impl Display for Location {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
         if let Some(state) = self.state.as_ref().filter(|s| !s.is_empty()) {
-            write!(f, "{}, {}, {}", self.name, state, self.country)
-        } else {
-            write!(f, "{}, {}", self.name, self.country)
+            write!(f, ", {state}")?;
+        }
+        // Locations imported from files can lack the country.
+        if !self.country.is_empty() {
+            write!(f, ", {}", self.country)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_zone_offsets() {
+        let winter: DateTime<Utc> = "2024-01-15T12:00:00Z".parse().unwrap();
+        let summer: DateTime<Utc> = "2024-07-15T12:00:00Z".parse().unwrap();
+        let kyiv = TimeZone::Named("Europe/Kyiv".to_string());
+        assert_eq!(kyiv.offset_at(winter), FixedOffset::east_opt(2 * 3600));
+        assert_eq!(kyiv.offset_at(summer), FixedOffset::east_opt(3 * 3600));
+        let offset = TimeZone::Offset(-5 * 3600);
+        assert_eq!(offset.offset_at(summer), FixedOffset::west_opt(5 * 3600));
+        assert_eq!(
+            TimeZone::Named("Mars/Olympus".to_string()).offset_at(summer),
+            None
+        );
+    }
+
+    #[test]
+    fn time_zones_as_text() {
+        for (text, timezone) in [
+            ("Europe/Kyiv", TimeZone::Named("Europe/Kyiv".to_string())),
+            ("+03:00", TimeZone::Offset(3 * 3600)),
+            ("-05:30", TimeZone::Offset(-(5 * 3600 + 1800))),
+        ] {
+            assert_eq!(TimeZone::try_from(text.to_string()).unwrap(), timezone);
+            assert_eq!(String::from(timezone), text);
         }
+        assert!(TimeZone::try_from("+3 hours".to_string()).is_err());
     }
 }
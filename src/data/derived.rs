@@ -0,0 +1,304 @@
+//! Meteorological metrics computed locally from temperature, humidity and wind,
+//! as providers are inconsistent about reporting them.
+
+use serde::{Deserialize, Serialize};
+use uom::si::f64::{MassDensity, Ratio, ThermodynamicTemperature, Velocity};
+use uom::si::mass_density::gram_per_cubic_meter;
+use uom::si::ratio::percent;
+use uom::si::thermodynamic_temperature::{degree_celsius, degree_fahrenheit};
+use uom::si::velocity::kilometer_per_hour;
+
+use super::Weather;
+
+/// Value reported by the provider, or computed locally when it is not.
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq)]
+pub struct Derived<T> {
+    pub value: T,
+    pub computed: bool,
+}
+
+impl<T> Derived<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Derived<U> {
+        Derived {
+            value: f(self.value),
+            computed: self.computed,
+        }
+    }
+
+    fn reported_or(reported: Option<T>, computed: impl FnOnce() -> Option<T>) -> Option<Self> {
+        match reported {
+            Some(value) => Some(Self {
+                value,
+                computed: false,
+            }),
+            None => computed().map(|value| Self {
+                value,
+                computed: true,
+            }),
+        }
+    }
+}
+
+/// Metrics of the weather, `None` where inputs are missing or out of the formula range.
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq)]
+pub struct Metrics {
+    pub feels_like: Option<Derived<ThermodynamicTemperature>>,
+    pub dew_point: Option<Derived<ThermodynamicTemperature>>,
+    // Never reported by providers, so always computed.
+    pub heat_index: Option<ThermodynamicTemperature>,
+    pub wind_chill: Option<ThermodynamicTemperature>,
+    pub humidex: Option<f64>,
+    pub absolute_humidity: Option<MassDensity>,
+    pub wet_bulb: Option<ThermodynamicTemperature>,
+}
+
+impl Metrics {
+    pub fn new(weather: &Weather) -> Self {
+        let t = weather.temperature;
+        let rh = weather.humidity;
+        let dew_point = Derived::reported_or(weather.dew_point, || rh.map(|rh| dew_point(t, rh)));
+        Self {
+            feels_like: Derived::reported_or(weather.feels_like, || match rh {
+                Some(rh) => Some(feels_like(t, rh, weather.wind_speed)),
+                // The heat index needs humidity, but the wind chill does not.
+                None => weather.wind_speed.and_then(|v| wind_chill(t, v)),
+            }),
+            heat_index: rh.and_then(|rh| heat_index(t, rh)),
+            wind_chill: weather.wind_speed.and_then(|v| wind_chill(t, v)),
+            humidex: dew_point.map(|td| humidex(t, td.value)),
+            absolute_humidity: rh.map(|rh| absolute_humidity(t, rh)),
+            wet_bulb: rh.and_then(|rh| wet_bulb(t, rh)),
+            dew_point,
+        }
+    }
+}
+
+/// Dew point by the Magnus formula with Sonntag constants.
+pub fn dew_point(t: ThermodynamicTemperature, rh: Ratio) -> ThermodynamicTemperature {
+    const A: f64 = 17.62;
+    const B: f64 = 243.12;
+    let t = t.get::<degree_celsius>();
+    let gamma = rh.get::<percent>().max(f64::MIN_POSITIVE).ln() - 100f64.ln() + A * t / (B + t);
+    ThermodynamicTemperature::new::<degree_celsius>(B * gamma / (A - gamma))
+}
+
+/// Heat index by the NWS algorithm with the Rothfusz regression, defined from 80 °F (26.7 °C).
+pub fn heat_index(t: ThermodynamicTemperature, rh: Ratio) -> Option<ThermodynamicTemperature> {
+    let t = t.get::<degree_fahrenheit>();
+    let rh = rh.get::<percent>();
+    if t < 80.0 {
+        return None;
+    }
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    let hi = if (simple + t) / 2.0 < 80.0 {
+        simple
+    } else {
+        let mut hi = -42.379 + 2.049_015_23 * t + 10.143_331_27 * rh
+            - 0.224_755_41 * t * rh
+            - 0.006_837_83 * t * t
+            - 0.054_817_17 * rh * rh
+            + 0.001_228_74 * t * t * rh
+            + 0.000_852_82 * t * rh * rh
+            - 0.000_001_99 * t * t * rh * rh;
+        if rh < 13.0 && t <= 112.0 {
+            hi -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+        } else if rh > 85.0 && t <= 87.0 {
+            hi += (rh - 85.0) / 10.0 * ((87.0 - t) / 5.0);
+        }
+        hi
+    };
+    Some(ThermodynamicTemperature::new::<degree_fahrenheit>(hi))
+}
+
+/// Wind chill by the NWS and Environment Canada formula,
+/// defined up to 10 °C and for wind over 4.8 km/h.
+pub fn wind_chill(t: ThermodynamicTemperature, v: Velocity) -> Option<ThermodynamicTemperature> {
+    let t = t.get::<degree_celsius>();
+    let v = v.get::<kilometer_per_hour>();
+    if t > 10.0 || v <= 4.8 {
+        return None;
+    }
+    let v = v.powf(0.16);
+    let wc = 13.12 + 0.6215 * t - 11.37 * v + 0.3965 * t * v;
+    Some(ThermodynamicTemperature::new::<degree_celsius>(wc))
+}
+
+/// Humidex of Environment Canada, a dimensionless value comparable to degrees Celsius.
+pub fn humidex(t: ThermodynamicTemperature, dew_point: ThermodynamicTemperature) -> f64 {
+    let vapor_pressure = 6.11 * (5417.7530 * (1.0 / 273.16 - 1.0 / dew_point.value)).exp();
+    t.get::<degree_celsius>() + 0.5555 * (vapor_pressure - 10.0)
+}
+
+/// Mass of water vapor in a cubic meter of air.
+pub fn absolute_humidity(t: ThermodynamicTemperature, rh: Ratio) -> MassDensity {
+    let t = t.get::<degree_celsius>();
+    // Saturation vapor pressure in hectopascals by the Bolton formula.
+    let saturation = 6.112 * (17.67 * t / (t + 243.5)).exp();
+    let density = saturation * rh.get::<percent>() * 2.1674 / (273.15 + t);
+    MassDensity::new::<gram_per_cubic_meter>(density)
+}
+
+/// Wet-bulb temperature by the Stull formula, defined from -20 °C to 50 °C and 5% to 99% of humidity.
+pub fn wet_bulb(t: ThermodynamicTemperature, rh: Ratio) -> Option<ThermodynamicTemperature> {
+    let t = t.get::<degree_celsius>();
+    let rh = rh.get::<percent>();
+    if !(-20.0..=50.0).contains(&t) || !(5.0..=99.0).contains(&rh) {
+        return None;
+    }
+    let tw = t * (0.151_977 * (rh + 8.313_659).sqrt()).atan() + (t + rh).atan()
+        - (rh - 1.676_331).atan()
+        + 0.003_918_38 * rh.powf(1.5) * (0.023_101 * rh).atan()
+        - 4.686_035;
+    Some(ThermodynamicTemperature::new::<degree_celsius>(tw))
+}
+
+/// Heat index when it is hot, wind chill when it is cold and windy, the temperature itself otherwise.
+pub fn feels_like(
+    t: ThermodynamicTemperature,
+    rh: Ratio,
+    wind: Option<Velocity>,
+) -> ThermodynamicTemperature {
+    heat_index(t, rh)
+        .or_else(|| wind.and_then(|v| wind_chill(t, v)))
+        .unwrap_or(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::stub::WeatherBuilder;
+
+    use super::*;
+
+    fn celsius(value: f64) -> ThermodynamicTemperature {
+        ThermodynamicTemperature::new::<degree_celsius>(value)
+    }
+
+    fn humidity(value: f64) -> Ratio {
+        Ratio::new::<percent>(value)
+    }
+
+    fn assert_near(actual: f64, expected: f64, tolerance: f64, case: &str) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{case}: expected {expected}, got {actual}"
+        );
+    }
+
+    // Reference values are rounded, so they are compared with half of the last digit.
+
+    #[test]
+    fn dew_points() {
+        // Sensirion SHT humidity sensors application note, Magnus formula.
+        for (t, rh, expected) in [(20.0, 50.0, 9.3), (30.0, 70.0, 23.9), (0.0, 80.0, -3.0)] {
+            let actual = dew_point(celsius(t), humidity(rh)).get::<degree_celsius>();
+            assert_near(actual, expected, 0.05, &format!("{t} °C, {rh}%"));
+        }
+    }
+
+    #[test]
+    fn heat_indexes() {
+        // NWS heat index chart, in degrees Fahrenheit.
+        for (t, rh, expected) in [
+            (80.0, 40.0, 80.0),
+            (90.0, 70.0, 106.0),
+            (96.0, 65.0, 121.0),
+            (100.0, 40.0, 109.0),
+            (84.0, 90.0, 98.0),
+        ] {
+            let t = ThermodynamicTemperature::new::<degree_fahrenheit>(t);
+            let actual = heat_index(t, humidity(rh)).unwrap();
+            let case = format!("{t:?}, {rh}%");
+            assert_near(actual.get::<degree_fahrenheit>(), expected, 0.5, &case);
+        }
+        assert_eq!(heat_index(celsius(20.0), humidity(50.0)), None);
+    }
+
+    #[test]
+    fn wind_chills() {
+        // Environment Canada wind chill index table.
+        for (t, v, expected) in [
+            (0.0, 10.0, -3.0),
+            (-10.0, 20.0, -18.0),
+            (-20.0, 30.0, -33.0),
+        ] {
+            let v = Velocity::new::<kilometer_per_hour>(v);
+            let actual = wind_chill(celsius(t), v).unwrap().get::<degree_celsius>();
+            assert_near(actual, expected, 0.5, &format!("{t} °C, {v:?}"));
+        }
+        let calm = Velocity::new::<kilometer_per_hour>(3.0);
+        assert_eq!(wind_chill(celsius(-10.0), calm), None);
+        let breeze = Velocity::new::<kilometer_per_hour>(20.0);
+        assert_eq!(wind_chill(celsius(15.0), breeze), None);
+    }
+
+    #[test]
+    fn humidexes() {
+        // Environment Canada humidex table by air temperature and dew point.
+        for (t, td, expected) in [(30.0, 15.0, 34.0), (35.0, 25.0, 47.0), (25.0, 20.0, 33.0)] {
+            let actual = humidex(celsius(t), celsius(td));
+            assert_near(actual, expected, 0.5, &format!("{t} °C, dew point {td} °C"));
+        }
+    }
+
+    #[test]
+    fn absolute_humidities() {
+        for (t, rh, expected) in [(20.0, 50.0, 8.65), (30.0, 80.0, 24.3), (0.0, 100.0, 4.85)] {
+            let actual = absolute_humidity(celsius(t), humidity(rh));
+            let case = format!("{t} °C, {rh}%");
+            assert_near(actual.get::<gram_per_cubic_meter>(), expected, 0.05, &case);
+        }
+    }
+
+    #[test]
+    fn wet_bulbs() {
+        // Stull, "Wet-Bulb Temperature from Relative Humidity and Air Temperature", 2011.
+        for (t, rh, expected) in [(20.0, 50.0, 13.7), (30.0, 60.0, 23.9)] {
+            let actual = wet_bulb(celsius(t), humidity(rh)).unwrap();
+            let case = format!("{t} °C, {rh}%");
+            assert_near(actual.get::<degree_celsius>(), expected, 0.15, &case);
+        }
+        assert_eq!(wet_bulb(celsius(20.0), humidity(2.0)), None);
+    }
+
+    #[test]
+    fn computed_only_when_not_reported() {
+        let mut weather = WeatherBuilder::new(-10.0)
+            .humidity(humidity(80.0))
+            .wind_speed(Velocity::new::<kilometer_per_hour>(20.0))
+            .dew_point(celsius(-12.0))
+            .build();
+        let metrics = Metrics::new(&weather);
+        let feels_like = metrics.feels_like.unwrap();
+        assert!(feels_like.computed);
+        assert_near(
+            feels_like.value.get::<degree_celsius>(),
+            -18.0,
+            0.5,
+            "feels like",
+        );
+        assert_eq!(
+            metrics.dew_point,
+            Some(Derived {
+                value: celsius(-12.0),
+                computed: false
+            })
+        );
+        assert_eq!(metrics.heat_index, None);
+
+        weather.humidity = None;
+        weather.dew_point = None;
+        let metrics = Metrics::new(&weather);
+        let wind_chill = metrics.wind_chill.unwrap();
+        assert_eq!(
+            metrics.feels_like,
+            Some(Derived {
+                value: wind_chill,
+                computed: true
+            })
+        );
+        assert_eq!(metrics.dew_point, None);
+
+        weather.wind_speed = None;
+        assert_eq!(Metrics::new(&weather).feels_like, None);
+    }
+}
@@ -1,16 +1,127 @@
+use std::io;
+use std::path::PathBuf;
+
+use proc_exit::{sysexits, Code};
 use thiserror::Error;
 
+use crate::api::Window;
+use crate::data::Provider;
+
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// Underlying error of a third-party crate, kept opaque so it can change without breaking users.
+pub type Source = Box<dyn std::error::Error + Send + Sync>;
+
+/// Errors of the library.
+///
+/// New variants can be added in minor releases, so match with a wildcard arm.
+/// Errors of HTTP and config crates are only available through [`std::error::Error::source`].
 #[derive(Error, Debug)]
 #[non_exhaustive]
 pub enum Error {
-    #[error("Got a bad response from the provider API")]
-    BadResponse,
+    #[error("Provider API key is invalid")]
+    InvalidApiKey,
+
+    #[error("Provider API quota or rate limit is exceeded")]
+    QuotaExceeded,
+
+    #[error("Budget of {limit} calls per {window} is spent for {provider:?} provider")]
+    BudgetExceeded {
+        provider: Provider,
+        limit: u32,
+        window: Window,
+    },
+
+    #[error("Location is not found by the provider API")]
+    LocationNotFound,
+
+    #[error("Provider API is unavailable, status code {0}")]
+    ProviderOutage(u16),
 
-    #[error("Internal local storage error")]
-    Confy(#[from] confy::ConfyError),
+    #[error("Provider API returned an unexpected status code {0}")]
+    UnexpectedStatus(u16),
 
     #[error("Failed to communicate with provider API")]
-    Reqwest(#[from] reqwest::Error),
+    Network(#[source] Source),
+
+    #[error("Got a malformed response from the provider API at \"{path}\": {reason}")]
+    MalformedResponse { path: String, reason: String },
+
+    #[error("Local config is corrupted or inaccessible")]
+    Config(#[source] Source),
+
+    #[error("Config {path:?} is invalid: {reason}")]
+    InvalidConfig { path: PathBuf, reason: String },
+
+    #[error("Config version {version} is not supported, the latest known is {supported}, consider upgrading")]
+    UnsupportedConfigVersion { version: i64, supported: u32 },
+
+    #[error("Failed to import: {0}")]
+    InvalidImport(String),
+
+    #[error("Profile \"{0}\" does not exist")]
+    ProfileNotFound(String),
+
+    #[error("Profile \"{0}\" already exists")]
+    ProfileExists(String),
+
+    #[error("Profile name \"{0}\" is invalid, only letters, digits, '-' and '_' are allowed")]
+    InvalidProfileName(String),
+
+    #[error("Failed to read certificates from {0:?}")]
+    Certificate(PathBuf, #[source] io::Error),
+
+    #[error("Proxy URL \"{0}\" is invalid")]
+    InvalidProxy(String, #[source] Source),
+
+    #[error("Failed to notify about \"{alert}\" alert: {reason}")]
+    Notification { alert: String, reason: String },
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Network(e.into())
+    }
+}
+
+impl From<confy::ConfyError> for Error {
+    fn from(e: confy::ConfyError) -> Self {
+        Error::Config(e.into())
+    }
+}
+
+impl Error {
+    /// Whether the same request can succeed later or with another provider.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Error::QuotaExceeded
+                | Error::BudgetExceeded { .. }
+                | Error::ProviderOutage(_)
+                | Error::Network(_)
+        )
+    }
+
+    /// Exit code of the process, see "Exit codes" in README.
+    pub fn exit_code(&self) -> Code {
+        match self {
+            Error::InvalidApiKey => sysexits::NO_PERM,
+            Error::QuotaExceeded | Error::BudgetExceeded { .. } => sysexits::TEMP_FAIL,
+            Error::LocationNotFound => sysexits::NO_INPUT,
+            Error::ProviderOutage(_) => sysexits::SERVICE_UNAVAILABLE,
+            Error::UnexpectedStatus(_) => sysexits::SOFTWARE_ERR,
+            Error::Network(_) => sysexits::IO_ERR,
+            Error::MalformedResponse { .. } => sysexits::PROTOCOL_ERR,
+            Error::InvalidImport(_) => sysexits::DATA_ERR,
+            Error::Notification { .. } => sysexits::IO_ERR,
+            Error::ProfileNotFound(_) | Error::ProfileExists(_) | Error::InvalidProfileName(_) => {
+                sysexits::USAGE_ERR
+            }
+            Error::Config(_)
+            | Error::InvalidConfig { .. }
+            | Error::UnsupportedConfigVersion { .. }
+            | Error::Certificate(..)
+            | Error::InvalidProxy(..) => sysexits::CONFIG_ERR,
+        }
+    }
 }
@@ -0,0 +1,319 @@
+//! Prometheus exporter polling weather of configured locations in the background.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use log::{info, warn};
+use uom::si::pressure::pascal;
+use uom::si::ratio::ratio;
+use uom::si::thermodynamic_temperature::degree_celsius;
+use uom::si::velocity::meter_per_second;
+
+use crate::api::{self, AsyncApi};
+use crate::data::{Location, Provider, Weather};
+use crate::error::Error;
+
+/// Upper bounds of fetch duration buckets, in seconds.
+const BUCKETS: [f64; 7] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Providers in failover order along with locations to poll and the latest metrics.
+pub struct Exporter {
+    apis: Vec<Box<dyn AsyncApi>>,
+    locations: Vec<Location>,
+    parallel: usize,
+    metrics: Mutex<Metrics>,
+}
+
+#[derive(Default)]
+struct Metrics {
+    /// Latest weather by location, dropped when no provider answers, so stale values are not exported.
+    readings: BTreeMap<String, (Provider, Weather)>,
+    durations: BTreeMap<String, Histogram>,
+    errors: BTreeMap<(String, &'static str), u64>,
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(BUCKETS) {
+            if value <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+impl Exporter {
+    pub fn new(apis: Vec<Box<dyn AsyncApi>>, locations: Vec<Location>, parallel: usize) -> Self {
+        assert!(!apis.is_empty(), "at least one provider should be given");
+        Self {
+            apis,
+            locations,
+            parallel,
+            metrics: Mutex::new(Metrics::default()),
+        }
+    }
+
+    /// Get weather for all locations once, at most `parallel` at a time.
+    pub async fn poll(&self) {
+        // Locations are passed by index, as a closure over references makes the future not `Send`.
+        api::concurrently(0..self.locations.len(), self.parallel, |i| {
+            self.poll_location(&self.locations[i])
+        })
+        .await;
+    }
+
+    /// Try providers in order like [`api::with_failover_async`], timing every attempt.
+    async fn poll_location(&self, location: &Location) {
+        let label = location.to_string();
+        for api in &self.apis {
+            let start = Instant::now();
+            let result = async {
+                let resolved = api.resolve_location(location).await?;
+                api.get_weather(&resolved).await
+            }
+            .await;
+
+            let provider = api.provider();
+            let mut metrics = self.metrics.lock().expect("lock should not be poisoned");
+            metrics
+                .durations
+                .entry(provider_label(provider))
+                .or_default()
+                .observe(start.elapsed().as_secs_f64());
+            match result {
+                Ok(weather) => {
+                    metrics.readings.insert(label, (provider, weather));
+                    return;
+                }
+                Err(e) => {
+                    warn!("\"{provider:?}\" provider failed for \"{label}\": {e}");
+                    *metrics
+                        .errors
+                        .entry((provider_label(provider), error_kind(&e)))
+                        .or_default() += 1;
+                    if !e.is_retryable() {
+                        break;
+                    }
+                }
+            }
+        }
+        self.metrics
+            .lock()
+            .expect("lock should not be poisoned")
+            .readings
+            .remove(&label);
+    }
+
+    /// Metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let metrics = self.metrics.lock().expect("lock should not be poisoned");
+        let mut out = String::new();
+
+        type Gauge = fn(&Weather) -> Option<f64>;
+        let gauges: [(&str, &str, Gauge); 4] = [
+            (
+                "weather_temperature_celsius",
+                "Temperature in degrees Celsius.",
+                |w| Some(w.temperature.get::<degree_celsius>()),
+            ),
+            (
+                "weather_humidity_ratio",
+                "Relative humidity from 0 to 1.",
+                |w| w.humidity.map(|h| h.get::<ratio>()),
+            ),
+            (
+                "weather_pressure_pascals",
+                "Atmospheric pressure in pascals.",
+                |w| w.pressure.map(|p| p.get::<pascal>()),
+            ),
+            (
+                "weather_wind_speed_meters_per_second",
+                "Wind speed in meters per second.",
+                |w| w.wind_speed.map(|v| v.get::<meter_per_second>()),
+            ),
+        ];
+        for (name, help, value) in gauges {
+            header(&mut out, name, help, "gauge");
+            for (location, (provider, weather)) in &metrics.readings {
+                if let Some(value) = value(weather) {
+                    let labels = format!(
+                        "location=\"{}\",provider=\"{}\"",
+                        escape(location),
+                        provider_label(*provider)
+                    );
+                    writeln!(out, "{name}{{{labels}}} {value}").expect("string is writable");
+                }
+            }
+        }
+
+        let name = "weather_fetch_duration_seconds";
+        header(
+            &mut out,
+            name,
+            "Duration of calls to providers.",
+            "histogram",
+        );
+        for (provider, histogram) in &metrics.durations {
+            for (bound, count) in BUCKETS.iter().zip(histogram.buckets) {
+                writeln!(
+                    out,
+                    "{name}_bucket{{provider=\"{provider}\",le=\"{bound}\"}} {count}"
+                )
+                .expect("string is writable");
+            }
+            let count = histogram.count;
+            writeln!(
+                out,
+                "{name}_bucket{{provider=\"{provider}\",le=\"+Inf\"}} {count}"
+            )
+            .expect("string is writable");
+            writeln!(
+                out,
+                "{name}_sum{{provider=\"{provider}\"}} {}",
+                histogram.sum
+            )
+            .expect("string is writable");
+            writeln!(out, "{name}_count{{provider=\"{provider}\"}} {count}")
+                .expect("string is writable");
+        }
+
+        let name = "weather_fetch_errors_total";
+        header(
+            &mut out,
+            name,
+            "Failed calls to providers by kind of error.",
+            "counter",
+        );
+        for ((provider, kind), count) in &metrics.errors {
+            writeln!(
+                out,
+                "{name}{{provider=\"{provider}\",kind=\"{kind}\"}} {count}"
+            )
+            .expect("string is writable");
+        }
+        out
+    }
+
+    /// Poll every `interval` and serve `/metrics` until the process is stopped,
+    /// the runtime should have IO and time enabled.
+    pub async fn serve(self, listener: TcpListener, interval: Duration) -> io::Result<()> {
+        info!("listening on {}", listener.local_addr()?);
+        let exporter = Arc::new(self);
+        let poller = exporter.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            // Polls of many locations can take longer than the interval, do not catch up.
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                poller.poll().await;
+            }
+        });
+
+        let app = Router::new()
+            .route("/metrics", get(metrics))
+            .with_state(exporter);
+        axum::Server::from_tcp(listener)
+            .map_err(io::Error::other)?
+            .serve(app.into_make_service())
+            .await
+            .map_err(io::Error::other)
+    }
+}
+
+async fn metrics(State(exporter): State<Arc<Exporter>>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        exporter.render(),
+    )
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}").expect("string is writable");
+}
+
+fn provider_label(provider: Provider) -> String {
+    format!("{provider:?}")
+}
+
+/// Label values can contain anything, so backslashes, quotes and line breaks are escaped.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('\n', r"\n")
+}
+
+/// Stable name of the error, as messages are localized and contain details.
+fn error_kind(error: &Error) -> &'static str {
+    match error {
+        Error::InvalidApiKey => "invalid_api_key",
+        Error::QuotaExceeded => "quota_exceeded",
+        Error::BudgetExceeded { .. } => "budget_exceeded",
+        Error::LocationNotFound => "location_not_found",
+        Error::ProviderOutage(_) => "provider_outage",
+        Error::UnexpectedStatus(_) => "unexpected_status",
+        Error::Network(_) => "network",
+        Error::MalformedResponse { .. } => "malformed_response",
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::stub::Stub;
+
+    use super::*;
+
+    #[test]
+    fn export_metrics() {
+        let apis: Vec<Box<dyn AsyncApi>> = vec![
+            Box::new(Stub::new(Provider::AccuWeather).failing(|| Error::ProviderOutage(503))),
+            Box::new(Stub::new(Provider::OpenWeather)),
+        ];
+        let office = Location::from_coordinates("49.5,24").map(|l| Location {
+            name: "Office \"A\"".to_string(),
+            ..l
+        });
+        let exporter = Exporter::new(apis, office.into_iter().collect(), 4);
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(exporter.poll());
+
+        let text = exporter.render();
+        let lines: Vec<_> = text.lines().collect();
+        for line in [
+            "# TYPE weather_temperature_celsius gauge",
+            r#"weather_temperature_celsius{location="Office \"A\"",provider="OpenWeather"} 49.5"#,
+            r#"weather_humidity_ratio{location="Office \"A\"",provider="OpenWeather"} 0.5"#,
+            r#"weather_fetch_duration_seconds_count{provider="AccuWeather"} 1"#,
+            r#"weather_fetch_duration_seconds_bucket{provider="OpenWeather",le="+Inf"} 1"#,
+            r#"weather_fetch_errors_total{provider="AccuWeather",kind="provider_outage"} 1"#,
+        ] {
+            assert!(lines.contains(&line), "{line} is missing in:\n{text}");
+        }
+        // Not reported values are not exported.
+        assert!(!text.contains("weather_pressure_pascals{"));
+        assert!(!text.contains(r#"weather_fetch_errors_total{provider="OpenWeather""#));
+    }
+}
@@ -0,0 +1,407 @@
+use std::env;
+use std::sync::OnceLock;
+
+use weather::data::Language;
+
+/// Messages shown to the user by the CLI itself.
+#[derive(Copy, Clone, Debug)]
+pub enum Message {
+    ProviderAlreadyConfigured,
+    ConfirmReconfigure,
+    ProviderConfigurationNotChanged,
+    InputApiKey,
+    IncorrectApiKey,
+    ProviderConfigurationSaved,
+    NoProviderConfigured,
+    ProviderNotConfigured,
+    NoSavedLocation,
+    LocationNotFound,
+    SelectLocation,
+    ChosenLocation,
+    CurrentWeather,
+    AnsweredBy,
+    SomeLocationsFailed,
+    Site,
+    Location,
+    Temperature,
+    FeelsLike,
+    DewPoint,
+    Humidity,
+    HeatIndex,
+    WindChill,
+    Humidex,
+    AbsoluteHumidity,
+    WetBulb,
+    Computed,
+    Observed,
+    Stale,
+    TimeZone,
+    UnknownTimeZone,
+    NoCoordinates,
+    AstronomicalDawn,
+    NauticalDawn,
+    CivilDawn,
+    Sunrise,
+    GoldenHourEnd,
+    SolarNoon,
+    GoldenHourStart,
+    Sunset,
+    CivilDusk,
+    NauticalDusk,
+    AstronomicalDusk,
+    PolarDay,
+    PolarNight,
+    DayLength,
+    Moon,
+    NewMoon,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    FullMoon,
+    WaningGibbous,
+    LastQuarter,
+    WaningCrescent,
+    Weather,
+    Note,
+    AmbiguousLocation,
+    #[cfg(feature = "server")]
+    Listening,
+    NoAlerts,
+    KnownLocationNotFound,
+    AlertTriggered,
+    AlertNotTriggered,
+    AlertsTriggered,
+    SomeAlertsFailed,
+    DaemonAlreadyRunning,
+    DaemonRunning,
+    DaemonNotRunning,
+    DaemonStopped,
+    Uptime,
+    Ago,
+    NotFetched,
+    Seconds,
+    Minutes,
+    Hours,
+    CallsToday,
+    CallsThisMinute,
+    Remaining,
+    Unlimited,
+    ConfigValid,
+    ConfirmResetConfig,
+    ConfigReset,
+    ConfigNotChanged,
+    ConfigSaved,
+    ConfirmEditAgain,
+    Active,
+    ApiKey,
+    Valid,
+    Invalid,
+    NotChecked,
+    LocationId,
+    SavedLocation,
+    ConfirmUnconfigure,
+    ProviderConfigurationRemoved,
+    ActiveProvider,
+    ConfirmClearLocation,
+    LocationCleared,
+    LocationNotChanged,
+    Current,
+    ProfileCreated,
+    ConfirmDeleteProfile,
+    ProfileDeleted,
+    ProfileNotChanged,
+    DefaultProfileNotDeletable,
+    Exported,
+    Imported,
+    ConflictKept,
+    ConflictReplaced,
+    UseOverwrite,
+    SkippedWithoutApiKey,
+    NoKnownLocations,
+}
+
+static LANGUAGE: OnceLock<Language> = OnceLock::new();
+
+/// Set language for all further messages, can be done only once.
+pub fn set_language(language: Language) {
+    if LANGUAGE.set(language).is_err() {
+        log::warn!("language is already set");
+    }
+}
+
+pub fn language() -> Language {
+    *LANGUAGE.get_or_init(system_language)
+}
+
+/// Guess language from the system locale, English is used by default.
+pub fn system_language() -> Language {
+    match system_locale("LC_MESSAGES") {
+        Some(locale) if locale.starts_with("uk") => Language::Uk,
+        _ => Language::En,
+    }
+}
+
+/// Get locale for the given category, the first non-empty variable wins, the same as in setlocale(3).
+pub fn system_locale(category: &str) -> Option<String> {
+    ["LC_ALL", category, "LANG"]
+        .into_iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// Translate message into the current language.
+pub fn tr(message: Message) -> &'static str {
+    match language() {
+        Language::En => en(message),
+        Language::Uk => uk(message),
+    }
+}
+
+/// Format number with the decimal separator of the current language.
+pub fn format_number(value: f64, precision: usize) -> String {
+    let number = format!("{value:.precision$}");
+    match language() {
+        Language::En => number,
+        Language::Uk => number.replace('.', ","),
+    }
+}
+
+fn en(message: Message) -> &'static str {
+    match message {
+        Message::ProviderAlreadyConfigured => "Provider is already configured.",
+        Message::ConfirmReconfigure => "Do you want to reconfigure?",
+        Message::ProviderConfigurationNotChanged => "Provider configuration has not changed.",
+        Message::InputApiKey => "Input provider API key",
+        Message::IncorrectApiKey => "Incorrect provider API key.",
+        Message::ProviderConfigurationSaved => "Successfully saved provider configuration.",
+        Message::NoProviderConfigured => "None of the providers is configured.",
+        Message::ProviderNotConfigured => "Provider is not configured.",
+        Message::NoSavedLocation => "No saved location for active provider.",
+        Message::LocationNotFound => "Sorry, cannot find any location for the given input.",
+        Message::SelectLocation => "Several locations have been found, select one of them",
+        Message::ChosenLocation => "Chosen location",
+        Message::CurrentWeather => "Current weather",
+        Message::AnsweredBy => "Answered by provider",
+        Message::SomeLocationsFailed => "Failed to get weather for some locations.",
+        Message::Site => "Site",
+        Message::Location => "Location",
+        Message::Temperature => "Temperature",
+        Message::FeelsLike => "Feels like",
+        Message::DewPoint => "Dew point",
+        Message::Humidity => "Humidity",
+        Message::HeatIndex => "Heat index",
+        Message::WindChill => "Wind chill",
+        Message::Humidex => "Humidex",
+        Message::AbsoluteHumidity => "Absolute humidity",
+        Message::WetBulb => "Wet-bulb temperature",
+        Message::Computed => "computed",
+        Message::Observed => "Observed",
+        Message::Stale => "stale",
+        Message::TimeZone => "Time zone",
+        Message::UnknownTimeZone => "Time zone of the location is unknown, the local one is used.",
+        Message::NoCoordinates => "Location has no coordinates.",
+        Message::AstronomicalDawn => "Astronomical dawn",
+        Message::NauticalDawn => "Nautical dawn",
+        Message::CivilDawn => "Civil dawn",
+        Message::Sunrise => "Sunrise",
+        Message::GoldenHourEnd => "Golden hour ends",
+        Message::SolarNoon => "Solar noon",
+        Message::GoldenHourStart => "Golden hour starts",
+        Message::Sunset => "Sunset",
+        Message::CivilDusk => "Civil dusk",
+        Message::NauticalDusk => "Nautical dusk",
+        Message::AstronomicalDusk => "Astronomical dusk",
+        Message::PolarDay => "Polar day, the sun does not set.",
+        Message::PolarNight => "Polar night, the sun does not rise.",
+        Message::DayLength => "Day length",
+        Message::Moon => "Moon",
+        Message::NewMoon => "new moon",
+        Message::WaxingCrescent => "waxing crescent",
+        Message::FirstQuarter => "first quarter",
+        Message::WaxingGibbous => "waxing gibbous",
+        Message::FullMoon => "full moon",
+        Message::WaningGibbous => "waning gibbous",
+        Message::LastQuarter => "last quarter",
+        Message::WaningCrescent => "waning crescent",
+        Message::Weather => "Weather",
+        Message::Note => "Note",
+        Message::AmbiguousLocation => "several locations found, the top match is taken",
+        #[cfg(feature = "server")]
+        Message::Listening => "Listening on",
+        Message::NoAlerts => "No alerts are defined in the config.",
+        Message::KnownLocationNotFound => "Known location is not found",
+        Message::AlertTriggered => "triggered",
+        Message::AlertNotTriggered => "ok",
+        Message::AlertsTriggered => "Some alerts are triggered.",
+        Message::SomeAlertsFailed => "Failed to check some alerts.",
+        Message::DaemonAlreadyRunning => "Daemon is already running, PID",
+        Message::DaemonRunning => "Daemon is running, PID",
+        Message::DaemonNotRunning => "Daemon is not running.",
+        Message::DaemonStopped => "Daemon has been stopped.",
+        Message::Uptime => "up for",
+        Message::Ago => "ago",
+        Message::NotFetched => "not fetched yet",
+        Message::Seconds => "s",
+        Message::Minutes => "min",
+        Message::Hours => "h",
+        Message::CallsToday => "Calls today",
+        Message::CallsThisMinute => "Calls this minute",
+        Message::Remaining => "remaining",
+        Message::Unlimited => "unlimited",
+        Message::ConfigValid => "Config is valid.",
+        Message::ConfirmResetConfig => "Do you want to reset config to defaults?",
+        Message::ConfigReset => "Config has been reset.",
+        Message::ConfigNotChanged => "Config has not changed.",
+        Message::ConfigSaved => "Successfully saved config.",
+        Message::ConfirmEditAgain => "Do you want to edit it again?",
+        Message::Active => "active",
+        Message::ApiKey => "API key",
+        Message::Valid => "valid",
+        Message::Invalid => "invalid",
+        Message::NotChecked => "not checked",
+        Message::LocationId => "Location identifier",
+        Message::SavedLocation => "Saved location",
+        Message::ConfirmUnconfigure => "Do you want to remove provider configuration?",
+        Message::ProviderConfigurationRemoved => "Successfully removed provider configuration.",
+        Message::ActiveProvider => "Active provider",
+        Message::ConfirmClearLocation => "Do you want to clear saved locations?",
+        Message::LocationCleared => "Saved locations have been cleared.",
+        Message::LocationNotChanged => "Saved locations have not changed.",
+        Message::Current => "current",
+        Message::ProfileCreated => "Successfully created profile.",
+        Message::ConfirmDeleteProfile => "Do you want to delete the profile with its config?",
+        Message::ProfileDeleted => "Profile has been deleted.",
+        Message::ProfileNotChanged => "Profile has not changed.",
+        Message::DefaultProfileNotDeletable => {
+            "Default profile cannot be deleted, use `config reset` instead."
+        }
+        Message::Exported => "Successfully exported config.",
+        Message::Imported => "Imported values",
+        Message::ConflictKept => "Kept current value on conflict",
+        Message::ConflictReplaced => "Replaced current value on conflict",
+        Message::UseOverwrite => "Use --overwrite to replace current values.",
+        Message::SkippedWithoutApiKey => "Skipped provider without API key",
+        Message::NoKnownLocations => "No known locations.",
+    }
+}
+
+fn uk(message: Message) -> &'static str {
+    match message {
+        Message::ProviderAlreadyConfigured => "Постачальника вже налаштовано.",
+        Message::ConfirmReconfigure => "Бажаєте налаштувати повторно?",
+        Message::ProviderConfigurationNotChanged => "Налаштування постачальника не змінено.",
+        Message::InputApiKey => "Введіть API-ключ постачальника",
+        Message::IncorrectApiKey => "Неправильний API-ключ постачальника.",
+        Message::ProviderConfigurationSaved => "Налаштування постачальника успішно збережено.",
+        Message::NoProviderConfigured => "Жодного постачальника не налаштовано.",
+        Message::ProviderNotConfigured => "Постачальника не налаштовано.",
+        Message::NoSavedLocation => "Немає збереженого місця для активного постачальника.",
+        Message::LocationNotFound => {
+            "На жаль, не вдалося знайти жодного місця за вказаним запитом."
+        }
+        Message::SelectLocation => "Знайдено кілька місць, оберіть одне з них",
+        Message::ChosenLocation => "Обране місце",
+        Message::CurrentWeather => "Поточна погода",
+        Message::AnsweredBy => "Відповів постачальник",
+        Message::SomeLocationsFailed => "Не вдалося отримати погоду для деяких місць.",
+        Message::Site => "Об'єкт",
+        Message::Location => "Місце",
+        Message::Temperature => "Температура",
+        Message::FeelsLike => "Відчувається як",
+        Message::DewPoint => "Точка роси",
+        Message::Humidity => "Вологість",
+        Message::HeatIndex => "Індекс спеки",
+        Message::WindChill => "Вітрове охолодження",
+        Message::Humidex => "Гумідекс",
+        Message::AbsoluteHumidity => "Абсолютна вологість",
+        Message::WetBulb => "Температура за вологим термометром",
+        Message::Computed => "обчислено",
+        Message::Observed => "Спостереження",
+        Message::Stale => "застаріло",
+        Message::TimeZone => "Часовий пояс",
+        Message::UnknownTimeZone => "Часовий пояс місця невідомий, використано місцевий.",
+        Message::NoCoordinates => "Місце не має координат.",
+        Message::AstronomicalDawn => "Астрономічний світанок",
+        Message::NauticalDawn => "Навігаційний світанок",
+        Message::CivilDawn => "Громадянський світанок",
+        Message::Sunrise => "Схід сонця",
+        Message::GoldenHourEnd => "Кінець золотої години",
+        Message::SolarNoon => "Сонячний полудень",
+        Message::GoldenHourStart => "Початок золотої години",
+        Message::Sunset => "Захід сонця",
+        Message::CivilDusk => "Громадянські сутінки",
+        Message::NauticalDusk => "Навігаційні сутінки",
+        Message::AstronomicalDusk => "Астрономічні сутінки",
+        Message::PolarDay => "Полярний день, сонце не заходить.",
+        Message::PolarNight => "Полярна ніч, сонце не сходить.",
+        Message::DayLength => "Тривалість дня",
+        Message::Moon => "Місяць",
+        Message::NewMoon => "новий місяць",
+        Message::WaxingCrescent => "молодий місяць",
+        Message::FirstQuarter => "перша чверть",
+        Message::WaxingGibbous => "місяць, що росте",
+        Message::FullMoon => "повний місяць",
+        Message::WaningGibbous => "місяць, що спадає",
+        Message::LastQuarter => "остання чверть",
+        Message::WaningCrescent => "старий місяць",
+        Message::Weather => "Погода",
+        Message::Note => "Примітка",
+        Message::AmbiguousLocation => "знайдено кілька місць, взято найкращий збіг",
+        #[cfg(feature = "server")]
+        Message::Listening => "Очікування запитів на",
+        Message::NoAlerts => "У налаштуваннях не визначено сповіщень.",
+        Message::KnownLocationNotFound => "Відоме місце не знайдено",
+        Message::AlertTriggered => "спрацювало",
+        Message::AlertNotTriggered => "гаразд",
+        Message::AlertsTriggered => "Деякі сповіщення спрацювали.",
+        Message::SomeAlertsFailed => "Не вдалося перевірити деякі сповіщення.",
+        Message::DaemonAlreadyRunning => "Фоновий процес уже запущено, PID",
+        Message::DaemonRunning => "Фоновий процес працює, PID",
+        Message::DaemonNotRunning => "Фоновий процес не запущено.",
+        Message::DaemonStopped => "Фоновий процес зупинено.",
+        Message::Uptime => "працює",
+        Message::Ago => "тому",
+        Message::NotFetched => "ще не отримано",
+        Message::Seconds => "с",
+        Message::Minutes => "хв",
+        Message::Hours => "год",
+        Message::CallsToday => "Викликів сьогодні",
+        Message::CallsThisMinute => "Викликів цієї хвилини",
+        Message::Remaining => "залишилось",
+        Message::Unlimited => "без обмежень",
+        Message::ConfigValid => "Налаштування коректні.",
+        Message::ConfirmResetConfig => "Бажаєте скинути налаштування до типових?",
+        Message::ConfigReset => "Налаштування скинуто.",
+        Message::ConfigNotChanged => "Налаштування не змінено.",
+        Message::ConfigSaved => "Налаштування успішно збережено.",
+        Message::ConfirmEditAgain => "Бажаєте відредагувати знову?",
+        Message::Active => "активний",
+        Message::ApiKey => "API-ключ",
+        Message::Valid => "дійсний",
+        Message::Invalid => "недійсний",
+        Message::NotChecked => "не перевірено",
+        Message::LocationId => "Ідентифікатор місця",
+        Message::SavedLocation => "Збережене місце",
+        Message::ConfirmUnconfigure => "Бажаєте видалити налаштування постачальника?",
+        Message::ProviderConfigurationRemoved => "Налаштування постачальника успішно видалено.",
+        Message::ActiveProvider => "Активний постачальник",
+        Message::ConfirmClearLocation => "Бажаєте очистити збережені місця?",
+        Message::LocationCleared => "Збережені місця очищено.",
+        Message::LocationNotChanged => "Збережені місця не змінено.",
+        Message::Current => "поточний",
+        Message::ProfileCreated => "Профіль успішно створено.",
+        Message::ConfirmDeleteProfile => "Бажаєте видалити профіль разом із налаштуваннями?",
+        Message::ProfileDeleted => "Профіль видалено.",
+        Message::ProfileNotChanged => "Профіль не змінено.",
+        Message::DefaultProfileNotDeletable => {
+            "Типовий профіль не можна видалити, використайте `config reset`."
+        }
+        Message::Exported => "Налаштування успішно експортовано.",
+        Message::Imported => "Імпортовано значень",
+        Message::ConflictKept => "Залишено поточне значення через конфлікт",
+        Message::ConflictReplaced => "Замінено поточне значення через конфлікт",
+        Message::UseOverwrite => "Використайте --overwrite, щоб замінити поточні значення.",
+        Message::SkippedWithoutApiKey => "Пропущено постачальника без API-ключа",
+        Message::NoKnownLocations => "Немає відомих місць.",
+    }
+}
@@ -0,0 +1,36 @@
+//! Weather data from several API providers behind one interface.
+//!
+//! The [`api::Api`] trait is implemented by every provider, [`data`] holds provider-independent
+//! types, and [`storage::Storage`] keeps API keys, locations and preferences without any prompts,
+//! so the crate can be used by other programs as well as by the `weather-cli` binary.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use weather::api::{self, Client, ClientSettings};
+//! use weather::data::{Language, Provider};
+//!
+//! # fn main() -> weather::error::Result<()> {
+//! let client = Arc::new(Client::new(&ClientSettings::default())?);
+//! let api = api::new(Provider::OpenWeather, client, "<API key>".into(), Language::En);
+//! let location = api.search_location("Kyiv")?.remove(0);
+//! let weather = api.get_weather(&location)?;
+//! println!("{location}: {}", weather.description);
+//! # Ok(())
+//! # }
+//! ```
+
+#![deny(unused_must_use)]
+#![deny(unsafe_code)]
+#![deny(clippy::unwrap_used)]
+
+pub mod alert;
+pub mod api;
+pub mod astro;
+pub mod data;
+pub mod error;
+#[cfg(feature = "server")]
+pub mod exporter;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod storage;
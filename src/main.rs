@@ -2,90 +2,790 @@
 #![deny(unsafe_code)]
 #![deny(clippy::unwrap_used)]
 
-use anyhow::Result;
+use std::future::Future;
+#[cfg(feature = "server")]
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use std::{env, fs, iter, process};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, Offset, Utc};
 use dialoguer::{Confirm, Password, Select};
+use proc_exit::{sysexits, Code};
+use uom::si::f64::ThermodynamicTemperature;
+use uom::si::mass_density::gram_per_cubic_meter;
+use uom::si::ratio::percent;
 use uom::si::thermodynamic_temperature::degree_celsius;
 use uom::si::Unit;
 
-use crate::cli::{prelude::*, Cli, Command};
-use crate::data::{Location, Provider, Weather};
-use crate::storage::Storage;
-use crate::ui::{eprintln, get_style_for_weather, println, sprintln, theme, with_spinner};
+use weather::alert;
+use weather::api::{self, AsyncApi, AsyncClient, ClientSettings, Quota};
+use weather::astro::{self, Moon, Phase, Sun};
+use weather::data::derived::{Derived, Metrics};
+use weather::data::{Language, Location, Provider, TimeZone, Weather};
+use weather::error::Error;
+#[cfg(feature = "server")]
+use weather::exporter::Exporter;
+#[cfg(feature = "server")]
+use weather::server::{self, Server};
+use weather::storage::{
+    self, parse_csv, parse_geojson, ImportReport, Profiles, Storage, DEFAULT_PROFILE,
+};
+
+use crate::cli::{
+    prelude::*, Cli, Command, ConfigCommand, DaemonCommand, HttpArgs, ImportFormat,
+    LocationsCommand, ProfileCommand, ProviderCommand, TimeZoneChoice,
+};
+use crate::daemon::{Cache, Daemon};
+use crate::i18n::{format_number, tr, Message};
+use crate::ui::{
+    art, eprintln, get_style_for_weather, println, sprintln, theme, with_spinner, wprintln, IconSet,
+};
 
-mod api;
+mod batch;
+mod check;
 mod cli;
-mod data;
-mod error;
-mod storage;
+mod daemon;
+mod i18n;
 mod ui;
 
-fn main() -> Result<()> {
+fn main() {
     human_panic::setup_panic!();
-    env_logger::init();
     let args = Cli::parse();
+    match args.command {
+        Command::Daemon {
+            command: DaemonCommand::Run { .. },
+        } => daemon::init_logger(),
+        _ => env_logger::init(),
+    }
+
+    if let Err(e) = run(args) {
+        let code = e
+            .downcast_ref::<Error>()
+            .map_or(Code::FAILURE, Error::exit_code);
+        eprintln(&format!("{e:#}"), code)
+    }
+}
+
+fn run(args: Cli) -> Result<()> {
+    if let Some(profile) = &args.profile {
+        storage::set_profile(profile)?;
+        if args.command.uses_profile() {
+            Profiles::new()?.require(profile)?;
+        }
+    }
 
     match args.command {
-        Command::Configure { provider, config } => {
+        Command::Configure {
+            provider,
+            config,
+            http,
+        } => {
             let mut storage = Storage::load(config.as_ref())?;
-            configure_provider(&mut storage, provider)?;
+            choose_language(&mut storage, None);
+            let settings = client_settings(&storage, http);
+            let client = create_client(&storage, config.as_ref(), &settings)?;
+            configure_provider(&mut storage, client, provider)?;
+            storage.store(config)?;
+        }
+        Command::Unconfigure {
+            provider,
+            yes,
+            config,
+        } => {
+            let mut storage = Storage::load(config.as_ref())?;
+            choose_language(&mut storage, None);
+            unconfigure_provider(&mut storage, provider, yes)?;
             storage.store(config)?;
         }
         Command::Get {
             provider,
-            location,
+            mut locations,
+            batch,
+            format,
+            lang,
+            icons,
+            art,
+            astro,
+            tz,
             config,
+            http,
         } => {
             let mut storage = Storage::load(config.as_ref())?;
+            let language = choose_language(&mut storage, lang);
             let provider = choose_active_provider(&mut storage, provider);
 
-            let api_key = storage.get_api_key(provider).to_owned();
-            let api = api::new(provider, api_key);
+            let settings = client_settings(&storage, http);
+            let client = create_client(&storage, config.as_ref(), &settings)?;
+            let providers: Vec<_> = iter::once(provider)
+                .chain(storage.get_fallback_providers(provider))
+                .collect();
+            let apis = create_apis(&storage, &client, &providers, language);
+
+            if let Some(batch) = batch {
+                let content = fs::read_to_string(&batch)
+                    .with_context(|| format!("Failed to read {batch:?}"))?;
+                let is_csv = ImportFormat::guess(&batch) == ImportFormat::Csv;
+                let mut sites = batch::parse(&content, is_csv)?;
+                batch::resolve_known(&storage, &mut sites);
+                let client = create_async_client(&storage, config.as_ref(), &settings)?;
+                let apis = create_async_apis(&storage, &client, &providers, language);
+                let rows =
+                    with_spinner(|| block_on(batch::fetch(&apis, &sites, settings.parallel)))?;
+                batch::show(&rows, format)?;
+                storage.store(config)?;
+                if let Some(code) = rows.iter().find_map(|row| row.code) {
+                    eprintln(tr(Message::SomeLocationsFailed), code)
+                }
+                return Ok(());
+            }
+
+            if locations.len() > 1 {
+                let locations = locations
+                    .iter()
+                    .map(|location| Ok(find_location(&storage, &apis, location)?.0))
+                    .collect::<Result<Vec<_>>>()?;
+                let client = create_async_client(&storage, config.as_ref(), &settings)?;
+                let apis = create_async_apis(&storage, &client, &providers, language);
+                let options = ShowOptions {
+                    icons: icons.resolve(),
+                    art,
+                    astro,
+                    tz,
+                    stale_after: storage.get_stale_after(),
+                };
+                let cache = Cache::load(&daemon::state_path(config.as_ref())?);
+                show_weathers(&apis, &cache, &locations, settings.parallel, options)?;
+                storage.store(config)?;
+                return Ok(());
+            }
+
+            let location = choose_location(&mut storage, &apis, locations.pop())?;
+            show_location(&location);
+
+            // Weather the daemon keeps fresh is shown without asking providers.
+            let cache = Cache::load(&daemon::state_path(config.as_ref())?);
+            let (weather, answered_by) = match cache.get(&location) {
+                Some(answer) => answer,
+                None => {
+                    let ((weather, resolved), answered_by) = with_spinner(|| {
+                        api::with_failover(&apis, |api| {
+                            let location = storage
+                                .get_saved_location(api.provider())
+                                .unwrap_or_else(|| location.clone());
+                            let resolved = api.resolve_location(&location)?;
+                            Ok((api.get_weather(&resolved)?, resolved))
+                        })
+                    })?;
+                    storage.update_saved_location(answered_by, resolved);
+                    (weather, answered_by)
+                }
+            };
+            if let Some(timezone) = &weather.timezone {
+                storage.update_timezone(&location, timezone.clone());
+            }
+            if answered_by != provider {
+                println(&format!("{}: {answered_by:?}", tr(Message::AnsweredBy)));
+            }
+            let options = ShowOptions {
+                icons: icons.resolve(),
+                art,
+                astro,
+                tz,
+                stale_after: storage.get_stale_after(),
+            };
+            show_weather(&location, weather, options);
+            storage.store(config)?;
+        }
+        Command::Astro {
+            location,
+            date,
+            tz,
+            config,
+            http,
+        } => {
+            let mut storage = Storage::load(config.as_ref())?;
+            let language = choose_language(&mut storage, None);
+            let location = match location {
+                None => match storage.get_shared_location() {
+                    None => eprintln(tr(Message::NoSavedLocation), sysexits::USAGE_ERR),
+                    Some(location) => location.clone(),
+                },
+                // Coordinates and known locations need no provider.
+                Some(text) => match Location::from_coordinates(&text)
+                    .or_else(|| storage.find_location(&text).cloned())
+                {
+                    Some(location) => location,
+                    None => {
+                        let provider = choose_active_provider(&mut storage, None);
+                        let settings = client_settings(&storage, http);
+                        let client = create_client(&storage, config.as_ref(), &settings)?;
+                        let providers: Vec<_> = iter::once(provider)
+                            .chain(storage.get_fallback_providers(provider))
+                            .collect();
+                        let apis = create_apis(&storage, &client, &providers, language);
+                        find_location(&storage, &apis, &text)?.0
+                    }
+                },
+            };
+            let (Some(lat), Some(lon)) = (location.lat, location.lon) else {
+                eprintln(tr(Message::NoCoordinates), sysexits::DATA_ERR)
+            };
+            show_location(&location);
+            let site = location.timezone;
+            if tz == TimeZoneChoice::Site && site.is_none() {
+                wprintln(tr(Message::UnknownTimeZone));
+            }
+            let date = date.unwrap_or_else(|| today(tz, site.as_ref()));
+            show_astro(date, lat, lon, tz, site.as_ref());
+        }
+        #[cfg(feature = "server")]
+        Command::Serve {
+            bind,
+            cache_ttl,
+            rate_limit,
+            config,
+            http,
+        } => {
+            let mut storage = Storage::load(config.as_ref())?;
+            let language = choose_language(&mut storage, None);
+            let provider = choose_active_provider(&mut storage, None);
+
+            let settings = client_settings(&storage, http);
+            let client = create_async_client(&storage, config.as_ref(), &settings)?;
+            let providers: Vec<_> = iter::once(provider)
+                .chain(storage.get_fallback_providers(provider))
+                .collect();
+            let server = Server::new(
+                create_async_apis(&storage, &client, &providers, language),
+                storage.get_locations().to_vec(),
+                server::Settings {
+                    cache_ttl: Duration::from_secs(cache_ttl),
+                    rate_limit,
+                },
+            );
+            let listener =
+                TcpListener::bind(bind).with_context(|| format!("Failed to listen on {bind}"))?;
+            println(&format!("{}: http://{bind}", tr(Message::Listening)));
+            block_on(server.serve(listener))??;
+        }
+        #[cfg(feature = "server")]
+        Command::Exporter {
+            bind,
+            interval,
+            config,
+            http,
+        } => {
+            let mut storage = Storage::load(config.as_ref())?;
+            let language = choose_language(&mut storage, None);
+            let provider = choose_active_provider(&mut storage, None);
+
+            let locations = polled_locations(&storage);
+            let settings = client_settings(&storage, http);
+            let client = create_async_client(&storage, config.as_ref(), &settings)?;
+            let providers: Vec<_> = iter::once(provider)
+                .chain(storage.get_fallback_providers(provider))
+                .collect();
+            let exporter = Exporter::new(
+                create_async_apis(&storage, &client, &providers, language),
+                locations,
+                settings.parallel,
+            );
+            let listener =
+                TcpListener::bind(bind).with_context(|| format!("Failed to listen on {bind}"))?;
+            println(&format!(
+                "{}: http://{bind}/metrics",
+                tr(Message::Listening)
+            ));
+            block_on(exporter.serve(listener, Duration::from_secs(interval)))??;
+        }
+        Command::Check { config, http } => {
+            let mut storage = Storage::load(config.as_ref())?;
+            let language = choose_language(&mut storage, None);
+            let provider = choose_active_provider(&mut storage, None);
+            if storage.get_alerts().is_empty() {
+                eprintln(tr(Message::NoAlerts), sysexits::USAGE_ERR)
+            }
 
-            let location = choose_location(&mut storage, api.as_ref(), location)?;
-            show_location(location);
+            let settings = client_settings(&storage, http);
+            let client = create_async_client(&storage, config.as_ref(), &settings)?;
+            let providers: Vec<_> = iter::once(provider)
+                .chain(storage.get_fallback_providers(provider))
+                .collect();
+            let apis = create_async_apis(&storage, &client, &providers, language);
+            // Webhooks are not calls to providers, so they are not accounted.
+            let webhooks = api::Client::new(&settings)?;
 
-            let weather = with_spinner(|| api.get_weather(location))?;
-            show_weather(&weather);
+            // Keep states next to the config, so every config has its own.
+            let path = Storage::path(config.as_ref())?.with_extension("alerts.toml");
+            let mut state = alert::State::load(&path)?;
+            let cache = Cache::load(&daemon::state_path(config.as_ref())?);
+            let code = check::check(
+                &storage,
+                &apis,
+                &cache,
+                &webhooks,
+                &mut state,
+                settings.parallel,
+            )?;
+            state.store(&path)?;
+            storage.store(config.as_ref())?;
+            if code == check::TRIGGERED {
+                eprintln(tr(Message::AlertsTriggered), code)
+            } else if code != Code::SUCCESS {
+                eprintln(tr(Message::SomeAlertsFailed), code)
+            }
+        }
+        Command::Quota { config } => {
+            let mut storage = Storage::load(config.as_ref())?;
+            choose_language(&mut storage, None);
+            let quota = load_quota(&storage, config.as_ref())?;
+            show_quota(&storage, &quota);
+        }
+        Command::Export {
+            file,
+            include_secrets,
+            config,
+        } => {
+            let mut storage = Storage::load(config.as_ref())?;
+            choose_language(&mut storage, None);
+            let export = toml::to_string(&storage.export(include_secrets))?;
+            match file {
+                None => print!("{export}"),
+                Some(file) => {
+                    fs::write(&file, export)
+                        .with_context(|| format!("Failed to write {file:?}"))?;
+                    sprintln(tr(Message::Exported));
+                }
+            }
+        }
+        Command::Import {
+            file,
+            format,
+            overwrite,
+            config,
+        } => {
+            let mut storage = Storage::load(config.as_ref())?;
+            choose_language(&mut storage, None);
+            let content =
+                fs::read_to_string(&file).with_context(|| format!("Failed to read {file:?}"))?;
+            let report = match format.unwrap_or_else(|| ImportFormat::guess(&file)) {
+                ImportFormat::Export => {
+                    let export = toml::from_str(&content)
+                        .map_err(|e| Error::InvalidImport(e.to_string()))?;
+                    storage.import(export, overwrite)?
+                }
+                ImportFormat::Csv => storage.import_locations(parse_csv(&content)?, overwrite),
+                ImportFormat::Geojson => {
+                    storage.import_locations(parse_geojson(&content)?, overwrite)
+                }
+            };
+            show_import_report(&report, overwrite);
             storage.store(config)?;
         }
+        Command::Locations { command } => match command {
+            LocationsCommand::List { config } => {
+                let mut storage = Storage::load(config.as_ref())?;
+                choose_language(&mut storage, None);
+                show_locations(&storage);
+            }
+            LocationsCommand::Clear { yes, config } => {
+                let mut storage = Storage::load(config.as_ref())?;
+                choose_language(&mut storage, None);
+                let confirmation = yes
+                    || Confirm::with_theme(theme())
+                        .with_prompt(tr(Message::ConfirmClearLocation))
+                        .default(false)
+                        .interact()?;
+                if confirmation {
+                    storage.clear_locations();
+                    sprintln(tr(Message::LocationCleared));
+                } else {
+                    println(tr(Message::LocationNotChanged));
+                }
+                storage.store(config)?;
+            }
+        },
+        Command::Profile { command } => {
+            // The chosen profile is not created implicitly, its language is taken only if it exists.
+            let profiles = Profiles::new()?;
+            let name = Some(storage::profile())
+                .filter(|&name| profiles.exists(name))
+                .unwrap_or(DEFAULT_PROFILE);
+            let mut storage = Storage::load(Some(profiles.path(name)))?;
+            choose_language(&mut storage, None);
+            manage_profiles(command)?;
+        }
+        Command::Config { command } => match command {
+            ConfigCommand::Path { config } => {
+                println!("{}", Storage::path(config)?.display());
+            }
+            ConfigCommand::Show {
+                show_secrets,
+                config,
+            } => {
+                let storage = Storage::load(config)?;
+                print!("{}", storage.to_toml(show_secrets));
+            }
+            ConfigCommand::Edit { config } => {
+                edit_config(&Storage::path(config)?)?;
+            }
+            ConfigCommand::Validate { config } => {
+                Storage::validate(&Storage::path(config)?)?;
+                sprintln(tr(Message::ConfigValid));
+            }
+            ConfigCommand::Reset { yes, config } => {
+                let confirmation = yes
+                    || Confirm::with_theme(theme())
+                        .with_prompt(tr(Message::ConfirmResetConfig))
+                        .default(false)
+                        .interact()?;
+                if confirmation {
+                    Storage::reset(config)?;
+                    sprintln(tr(Message::ConfigReset));
+                } else {
+                    println(tr(Message::ConfigNotChanged));
+                }
+            }
+        },
+        Command::Daemon { command } => match command {
+            DaemonCommand::Run {
+                interval,
+                config,
+                http,
+            } => {
+                let mut storage = Storage::load(config.as_ref())?;
+                let language = choose_language(&mut storage, None);
+                let provider = choose_active_provider(&mut storage, None);
+
+                let locations = polled_locations(&storage);
+                let mut alerts = Vec::new();
+                for alert in storage.get_alerts() {
+                    // Alerts are checked against the polled copy of their location.
+                    let target = check::alert_location(&storage, alert)
+                        .and_then(|target| locations.iter().find(|l| l.name == target.name));
+                    match target {
+                        Some(location) => alerts.push((alert.clone(), location.clone())),
+                        None => wprintln(&format!(
+                            "{}: {}",
+                            alert.name,
+                            tr(Message::KnownLocationNotFound)
+                        )),
+                    }
+                }
+                let mut schedule = storage.get_daemon_settings().clone();
+                if let Some(interval) = interval {
+                    schedule.interval = interval;
+                }
+
+                let settings = client_settings(&storage, http);
+                let client = create_async_client(&storage, config.as_ref(), &settings)?;
+                let providers: Vec<_> = iter::once(provider)
+                    .chain(storage.get_fallback_providers(provider))
+                    .collect();
+                let daemon = Daemon {
+                    apis: create_async_apis(&storage, &client, &providers, language),
+                    webhooks: Arc::new(api::Client::new(&settings)?),
+                    locations,
+                    alerts,
+                    schedule,
+                    parallel: settings.parallel,
+                    path: daemon::state_path(config.as_ref())?,
+                    alerts_path: Storage::path(config.as_ref())?.with_extension("alerts.toml"),
+                };
+                storage.store(config.as_ref())?;
+                block_on(daemon.run())??;
+            }
+            DaemonCommand::Status { config } => {
+                choose_language(&mut Storage::load(config.as_ref())?, None);
+                daemon::status(&daemon::state_path(config.as_ref())?)?;
+            }
+            DaemonCommand::Stop { config } => {
+                choose_language(&mut Storage::load(config.as_ref())?, None);
+                daemon::stop(&daemon::state_path(config.as_ref())?)?;
+            }
+        },
+        Command::Provider { command } => match command {
+            ProviderCommand::List {
+                no_check,
+                config,
+                http,
+            } => {
+                let mut storage = Storage::load(config.as_ref())?;
+                let language = choose_language(&mut storage, None);
+                let settings = client_settings(&storage, http);
+                let client = (!no_check)
+                    .then(|| create_async_client(&storage, config.as_ref(), &settings))
+                    .transpose()?;
+                show_providers(&storage, client, language, settings.parallel)?;
+            }
+        },
     }
 
     Ok(())
 }
 
-fn configure_provider(storage: &mut Storage, provider: Provider) -> Result<()> {
+fn client_settings(storage: &Storage, args: HttpArgs) -> ClientSettings {
+    let mut settings = storage.get_client_settings().clone();
+    if let Some(timeout) = args.timeout {
+        settings.timeout = timeout;
+    }
+    if let Some(read_timeout) = args.read_timeout {
+        settings.read_timeout = read_timeout;
+    }
+    if let Some(parallel) = args.parallel {
+        settings.parallel = parallel;
+    }
+    settings.proxy = args.proxy.or(settings.proxy);
+    settings.no_proxy = args.no_proxy.or(settings.no_proxy);
+    settings.ca_cert = args.ca_cert.or(settings.ca_cert);
+    settings.min_tls_version = args.min_tls_version.or(settings.min_tls_version);
+    settings
+}
+
+fn create_client(
+    storage: &Storage,
+    config: Option<&PathBuf>,
+    settings: &ClientSettings,
+) -> Result<Arc<api::Client>> {
+    let quota = load_quota(storage, config)?;
+    Ok(Arc::new(api::Client::new(settings)?.with_quota(quota)))
+}
+
+fn create_async_client(
+    storage: &Storage,
+    config: Option<&PathBuf>,
+    settings: &ClientSettings,
+) -> Result<Arc<AsyncClient>> {
+    let quota = load_quota(storage, config)?;
+    Ok(Arc::new(AsyncClient::new(settings)?.with_quota(quota)))
+}
+
+/// Known locations along with the saved one, unless it is known too.
+fn polled_locations(storage: &Storage) -> Vec<Location> {
+    let mut locations = storage.get_locations().to_vec();
+    if let Some(shared) = storage.get_shared_location() {
+        if !locations.iter().any(|l| l.name == shared.name) {
+            locations.push(shared.clone());
+        }
+    }
+    if locations.is_empty() {
+        eprintln(tr(Message::NoSavedLocation), sysexits::USAGE_ERR)
+    }
+    locations
+}
+
+fn create_apis(
+    storage: &Storage,
+    client: &Arc<api::Client>,
+    providers: &[Provider],
+    language: Language,
+) -> Vec<Box<dyn api::Api>> {
+    providers
+        .iter()
+        .map(|&p| {
+            let api_key = storage.get_api_key(p).to_owned();
+            api::new(p, client.clone(), api_key, language)
+        })
+        .collect()
+}
+
+fn create_async_apis(
+    storage: &Storage,
+    client: &Arc<AsyncClient>,
+    providers: &[Provider],
+    language: Language,
+) -> Vec<Box<dyn AsyncApi>> {
+    providers
+        .iter()
+        .map(|&p| {
+            let api_key = storage.get_api_key(p).to_owned();
+            api::new_async(p, client.clone(), api_key, language)
+        })
+        .collect()
+}
+
+/// Run the future to completion, the blocking client must not be used inside.
+fn block_on<F: Future>(future: F) -> Result<F::Output> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    Ok(runtime.block_on(future))
+}
+
+fn load_quota(storage: &Storage, config: Option<&PathBuf>) -> Result<Arc<Quota>> {
+    // Keep usage next to the config, so every config has its own.
+    let path = Storage::path(config)?.with_extension("quota.toml");
+    Ok(Arc::new(Quota::load(path, storage.get_limits())?))
+}
+
+fn configure_provider(
+    storage: &mut Storage,
+    client: Arc<api::Client>,
+    provider: Provider,
+) -> Result<()> {
     if storage.is_provider_configured(provider) {
-        println("Provider is already configured.");
+        println(tr(Message::ProviderAlreadyConfigured));
         let confirmation = Confirm::with_theme(theme())
-            .with_prompt("Do you want to reconfigure?")
+            .with_prompt(tr(Message::ConfirmReconfigure))
             .interact()?;
         if !confirmation {
-            println("Provider configuration has not changed.");
+            println(tr(Message::ProviderConfigurationNotChanged));
             return Ok(());
         }
     }
 
     let api_key: String = Password::with_theme(theme())
-        .with_prompt("Input provider API key")
+        .with_prompt(tr(Message::InputApiKey))
         .interact()?;
-    let api = api::new(provider, api_key.clone());
+    let api = api::new(provider, client, api_key.clone(), i18n::language());
 
     let is_correct_api_key = with_spinner(|| api.is_valid())?;
     if !is_correct_api_key {
-        eprintln("Incorrect provider API key.")
+        eprintln(tr(Message::IncorrectApiKey), sysexits::NO_PERM)
     }
 
     storage.configure_provider(provider, api_key);
-    sprintln("Successfully saved provider configuration.");
+    sprintln(tr(Message::ProviderConfigurationSaved));
 
     Ok(())
 }
 
+fn unconfigure_provider(storage: &mut Storage, provider: Provider, yes: bool) -> Result<()> {
+    if !storage.is_provider_configured(provider) {
+        eprintln(tr(Message::ProviderNotConfigured), sysexits::USAGE_ERR)
+    }
+
+    let confirmation = yes
+        || Confirm::with_theme(theme())
+            .with_prompt(tr(Message::ConfirmUnconfigure))
+            .default(false)
+            .interact()?;
+    if !confirmation {
+        println(tr(Message::ProviderConfigurationNotChanged));
+        return Ok(());
+    }
+
+    let was_active = storage.get_active_provider() == Some(provider);
+    storage.unconfigure_provider(provider);
+    sprintln(tr(Message::ProviderConfigurationRemoved));
+    if was_active {
+        match storage.get_active_provider() {
+            Some(active) => println(&format!("{}: {active:?}", tr(Message::ActiveProvider))),
+            None => println(tr(Message::NoProviderConfigured)),
+        }
+    }
+
+    Ok(())
+}
+
+fn manage_profiles(command: ProfileCommand) -> Result<()> {
+    let profiles = Profiles::new()?;
+    match command {
+        ProfileCommand::List => {
+            for name in profiles.list()? {
+                if name == storage::profile() {
+                    println!("{name} ({})", tr(Message::Current));
+                } else {
+                    println!("{name}");
+                }
+            }
+        }
+        ProfileCommand::Create { name } => {
+            profiles.create(&name)?;
+            sprintln(tr(Message::ProfileCreated));
+        }
+        ProfileCommand::Copy { from, to } => {
+            profiles.copy(&from, &to)?;
+            sprintln(tr(Message::ProfileCreated));
+        }
+        ProfileCommand::Delete { name, yes } => {
+            if name == DEFAULT_PROFILE {
+                eprintln(tr(Message::DefaultProfileNotDeletable), sysexits::USAGE_ERR)
+            }
+            let confirmation = yes
+                || Confirm::with_theme(theme())
+                    .with_prompt(tr(Message::ConfirmDeleteProfile))
+                    .default(false)
+                    .interact()?;
+            if confirmation {
+                profiles.delete(&name)?;
+                sprintln(tr(Message::ProfileDeleted));
+            } else {
+                println(tr(Message::ProfileNotChanged));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Edit a copy of the config, so an invalid one never replaces the working config.
+fn edit_config(path: &Path) -> Result<()> {
+    if !path.exists() {
+        // Let confy create the default config.
+        Storage::load(Some(path))?;
+    }
+    let draft = path.with_extension("draft.toml");
+    fs::copy(path, &draft)?;
+    loop {
+        open_editor(&draft)?;
+        match Storage::replace(path, &draft) {
+            Ok(()) => {
+                sprintln(tr(Message::ConfigSaved));
+                return Ok(());
+            }
+            Err(e @ Error::InvalidConfig { .. }) => {
+                wprintln(&e.to_string());
+                let confirmation = Confirm::with_theme(theme())
+                    .with_prompt(tr(Message::ConfirmEditAgain))
+                    .default(true)
+                    .interact()?;
+                if !confirmation {
+                    fs::remove_file(&draft)?;
+                    println(tr(Message::ConfigNotChanged));
+                    return Ok(());
+                }
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+fn open_editor(path: &Path) -> Result<()> {
+    let default = if cfg!(windows) { "notepad" } else { "vi" };
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| default.to_string());
+    // Editor can come with arguments, e.g. "code --wait".
+    let mut args = editor.split_whitespace();
+    let program = args.next().unwrap_or(default);
+    let status = process::Command::new(program)
+        .args(args)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to run editor \"{editor}\""))?;
+    if !status.success() {
+        bail!("Editor \"{editor}\" exited with {status}");
+    }
+    Ok(())
+}
+
+fn choose_language(storage: &mut Storage, language: Option<Language>) -> Language {
+    if let Some(language) = language {
+        storage.set_language(language);
+    }
+    let language = storage.get_language().unwrap_or_else(i18n::system_language);
+    i18n::set_language(language);
+    language
+}
+
 fn choose_active_provider(storage: &mut Storage, provider: Option<Provider>) -> Provider {
     match provider {
         None => {
             let Some(provider) = storage.get_active_provider() else {
-                eprintln("None of the providers is configured.")
+                eprintln(tr(Message::NoProviderConfigured), sysexits::USAGE_ERR)
             };
             provider
         }
@@ -94,39 +794,28 @@ fn choose_active_provider(storage: &mut Storage, provider: Option<Provider>) ->
                 storage.mark_provider_active(provider);
                 provider
             } else {
-                eprintln("Provider is not configured.")
+                eprintln(tr(Message::ProviderNotConfigured), sysexits::USAGE_ERR)
             }
         }
     }
 }
 
-fn choose_location<'a>(
-    storage: &'a mut Storage,
-    api: &dyn api::Api,
+fn choose_location(
+    storage: &mut Storage,
+    apis: &[Box<dyn api::Api>],
     location_str: Option<String>,
-) -> Result<&'a Location> {
-    let provider = api.provider();
+) -> Result<Location> {
+    let provider = apis[0].provider();
     let location = match location_str {
         None => match storage.get_saved_location(provider) {
-            None => eprintln("No saved location for active provider."),
+            None => eprintln(tr(Message::NoSavedLocation), sysexits::USAGE_ERR),
             Some(location) => location,
         },
         Some(location_str) => {
-            let mut locations = with_spinner(|| api.search_location(&location_str))?;
-            let location = match locations.len() {
-                0 => eprintln("Sorry, cannot find any location for the given input."),
-                1 => locations.swap_remove(0),
-                _ => {
-                    let selection = Select::with_theme(theme())
-                        .default(0)
-                        .items(&locations)
-                        .with_prompt("Several locations have been found, select one of them")
-                        .report(false)
-                        .interact()?;
-                    locations.swap_remove(selection)
-                }
-            };
-            storage.save_location(provider, location);
+            let (location, found_by) = find_location(storage, apis, &location_str)?;
+            if storage.get_shared_location() != Some(&location) {
+                storage.save_location(found_by, location);
+            }
             storage
                 .get_saved_location(provider)
                 .expect("location should be saved")
@@ -136,20 +825,435 @@ fn choose_location<'a>(
     Ok(location)
 }
 
+/// Find a known location or search for it, asking to select one of several found.
+/// Return the location along with the provider that found it.
+fn find_location(
+    storage: &Storage,
+    apis: &[Box<dyn api::Api>],
+    location_str: &str,
+) -> Result<(Location, Provider)> {
+    // Known locations are chosen without searching.
+    if let Some(location) = storage.find_location(location_str) {
+        return Ok((location.clone(), apis[0].provider()));
+    }
+    let (mut locations, found_by) =
+        with_spinner(|| api::with_failover(apis, |api| api.search_location(location_str)))?;
+    let location = match locations.len() {
+        0 => eprintln(tr(Message::LocationNotFound), sysexits::NO_INPUT),
+        1 => locations.swap_remove(0),
+        _ => {
+            let selection = Select::with_theme(theme())
+                .default(0)
+                .items(&locations)
+                .with_prompt(tr(Message::SelectLocation))
+                .report(false)
+                .interact()?;
+            locations.swap_remove(selection)
+        }
+    };
+    Ok((location, found_by))
+}
+
 fn show_location(location: &Location) {
     println(&format!(
-        "Chosen location: {}",
+        "{}: {}",
+        tr(Message::ChosenLocation),
         theme().defaults_style.apply_to(location)
     ));
 }
 
-fn show_weather(weather: &Weather) {
-    let style = get_style_for_weather(&weather.description);
-    let weather_str = format!(
-        "{}, {:.0}{}",
-        weather.description,
-        weather.temperature.get::<degree_celsius>(),
-        degree_celsius::abbreviation()
+/// How weather is shown by `get`.
+#[derive(Copy, Clone)]
+struct ShowOptions {
+    icons: IconSet,
+    art: bool,
+    astro: bool,
+    tz: TimeZoneChoice,
+    stale_after: Duration,
+}
+
+fn show_weather(location: &Location, mut weather: Weather, options: ShowOptions) {
+    // The sun position is more accurate than the flag some providers derive from their own forecast.
+    // It is taken at the observation time, as weather kept by the daemon can be hours old.
+    if let (Some(lat), Some(lon)) = (location.lat, location.lon) {
+        let observed = weather.observed.unwrap_or_else(Utc::now);
+        weather.is_day = astro::is_day(observed, lat, lon);
+    }
+    let weather = &weather;
+    let style = get_style_for_weather(weather);
+    let description = match options.icons.icon(weather.condition, weather.is_day) {
+        Some(icon) => format!("{icon} {}", weather.description),
+        None => weather.description.clone(),
+    };
+    let temperature = format_temperature(weather.temperature);
+
+    if options.art {
+        println(&format!("{}:", tr(Message::CurrentWeather)));
+        for (i, line) in art(weather.condition, weather.is_day).iter().enumerate() {
+            // Place text next to the middle of the art, like wttr.in does.
+            let text = match i {
+                1 => description.as_str(),
+                2 => temperature.as_str(),
+                _ => "",
+            };
+            println!("{} {}", style.apply_to(line), style.apply_to(text));
+        }
+    } else {
+        let weather_str = format!("{description}, {temperature}");
+        println(&format!(
+            "{}: {}",
+            tr(Message::CurrentWeather),
+            style.apply_to(weather_str)
+        ));
+    }
+
+    let site = weather.timezone.as_ref().or(location.timezone.as_ref());
+    if options.tz == TimeZoneChoice::Site && site.is_none() {
+        wprintln(tr(Message::UnknownTimeZone));
+    }
+    if let Some(observed) = weather.observed {
+        let offset = display_offset(observed, options.tz, site);
+        let mut line = format!(
+            "{}: {}",
+            tr(Message::Observed),
+            observed.with_timezone(&offset).format("%H:%M (%:z)")
+        );
+        // Clocks of providers can be a bit ahead, then the age is negative.
+        if (Utc::now() - observed)
+            .to_std()
+            .is_ok_and(|age| age > options.stale_after)
+        {
+            line.push_str(&format!(", {}", tr(Message::Stale)));
+        }
+        println(&line);
+    }
+
+    let metrics = Metrics::new(weather);
+    if let Some(feels_like) = metrics.feels_like {
+        let value = format_derived(feels_like.map(format_temperature));
+        println(&format!("{}: {value}", tr(Message::FeelsLike)));
+    }
+    if let Some(humidity) = weather.humidity {
+        let value = format!("{}%", format_number(humidity.get::<percent>(), 0));
+        println(&format!("{}: {value}", tr(Message::Humidity)));
+    }
+    if let Some(dew_point) = metrics.dew_point {
+        let value = format_derived(dew_point.map(format_temperature));
+        println(&format!("{}: {value}", tr(Message::DewPoint)));
+    }
+    // Providers never report these, so they are always computed.
+    let computed = [
+        (
+            Message::HeatIndex,
+            metrics.heat_index.map(format_temperature),
+        ),
+        (
+            Message::WindChill,
+            metrics.wind_chill.map(format_temperature),
+        ),
+        (
+            Message::Humidex,
+            metrics.humidex.map(|h| format_number(h, 0)),
+        ),
+        (
+            Message::AbsoluteHumidity,
+            metrics.absolute_humidity.map(|density| {
+                format!(
+                    "{} {}",
+                    format_number(density.get::<gram_per_cubic_meter>(), 1),
+                    gram_per_cubic_meter::abbreviation()
+                )
+            }),
+        ),
+        (Message::WetBulb, metrics.wet_bulb.map(format_temperature)),
+    ];
+    for (message, value) in computed {
+        if let Some(value) = value {
+            let value = format_derived(Derived {
+                value,
+                computed: true,
+            });
+            println(&format!("{}: {value}", tr(message)));
+        }
+    }
+
+    if options.astro {
+        if let (Some(lat), Some(lon)) = (location.lat, location.lon) {
+            show_astro(today(options.tz, site), lat, lon, options.tz, site);
+        }
+    }
+}
+
+/// Offset to show the time with, the local one is used when the site zone is unknown.
+fn display_offset(at: DateTime<Utc>, tz: TimeZoneChoice, site: Option<&TimeZone>) -> FixedOffset {
+    match tz {
+        TimeZoneChoice::Local => None,
+        TimeZoneChoice::Site => site.and_then(|zone| zone.offset_at(at)),
+        TimeZoneChoice::Utc => Some(Utc.fix()),
+    }
+    .unwrap_or_else(|| *at.with_timezone(&Local).offset())
+}
+
+fn today(tz: TimeZoneChoice, site: Option<&TimeZone>) -> NaiveDate {
+    let now = Utc::now();
+    now.with_timezone(&display_offset(now, tz, site))
+        .date_naive()
+}
+
+/// Show sun events in the chosen time zone along with the moon phase at solar noon.
+fn show_astro(date: NaiveDate, lat: f64, lon: f64, tz: TimeZoneChoice, site: Option<&TimeZone>) {
+    let sun = Sun::on(date, lat, lon);
+    let offset = display_offset(sun.solar_noon, tz, site);
+    println(&format!("{}: {offset}", tr(Message::TimeZone)));
+    let crossings = [
+        (
+            sun.astronomical_twilight,
+            Message::AstronomicalDawn,
+            Message::AstronomicalDusk,
+        ),
+        (
+            sun.nautical_twilight,
+            Message::NauticalDawn,
+            Message::NauticalDusk,
+        ),
+        (sun.civil_twilight, Message::CivilDawn, Message::CivilDusk),
+        (sun.day, Message::Sunrise, Message::Sunset),
+        (
+            sun.golden_hour,
+            Message::GoldenHourEnd,
+            Message::GoldenHourStart,
+        ),
+    ];
+    let mut events: Vec<_> = crossings
+        .iter()
+        .filter_map(|(crossing, dawn, _)| Some((*dawn, crossing.as_ref()?.rising)))
+        .collect();
+    events.push((Message::SolarNoon, sun.solar_noon));
+    events.extend(
+        crossings
+            .iter()
+            .rev()
+            .filter_map(|(crossing, _, dusk)| Some((*dusk, crossing.as_ref()?.setting))),
     );
-    println(&format!("Current weather: {}", style.apply_to(weather_str)));
+    for (message, at) in events {
+        let time = at.with_timezone(&offset).format("%H:%M");
+        println(&format!("{}: {time}", tr(message)));
+    }
+
+    if sun.day.is_some() {
+        let minutes = sun.day_length.num_minutes();
+        println(&format!(
+            "{}: {} {} {} {}",
+            tr(Message::DayLength),
+            minutes / 60,
+            tr(Message::Hours),
+            minutes % 60,
+            tr(Message::Minutes)
+        ));
+    } else if sun.day_length.is_zero() {
+        println(tr(Message::PolarNight));
+    } else {
+        println(tr(Message::PolarDay));
+    }
+
+    let moon = Moon::at(sun.solar_noon);
+    let phase = match moon.phase {
+        Phase::New => Message::NewMoon,
+        Phase::WaxingCrescent => Message::WaxingCrescent,
+        Phase::FirstQuarter => Message::FirstQuarter,
+        Phase::WaxingGibbous => Message::WaxingGibbous,
+        Phase::Full => Message::FullMoon,
+        Phase::WaningGibbous => Message::WaningGibbous,
+        Phase::LastQuarter => Message::LastQuarter,
+        Phase::WaningCrescent => Message::WaningCrescent,
+    };
+    println(&format!(
+        "{}: {}, {}%",
+        tr(Message::Moon),
+        tr(phase),
+        format_number(moon.illumination * 100.0, 0)
+    ));
+}
+
+fn format_temperature(temperature: ThermodynamicTemperature) -> String {
+    format!(
+        "{}{}",
+        format_number(temperature.get::<degree_celsius>(), 0),
+        degree_celsius::abbreviation()
+    )
+}
+
+/// Mark values not reported by the provider, so they are not taken for measurements.
+fn format_derived(derived: Derived<String>) -> String {
+    if derived.computed {
+        format!("{} ({})", derived.value, tr(Message::Computed))
+    } else {
+        derived.value
+    }
+}
+
+/// Get weather for all locations concurrently and show it in the given order.
+fn show_weathers(
+    apis: &[Box<dyn AsyncApi>],
+    cache: &Cache,
+    locations: &[Location],
+    parallel: usize,
+    options: ShowOptions,
+) -> Result<()> {
+    let results = with_spinner(|| {
+        block_on(api::concurrently(
+            locations,
+            parallel,
+            |location| async move {
+                if let Some(answer) = cache.get(location) {
+                    return Ok(answer);
+                }
+                api::with_failover_async(apis, move |api| async move {
+                    let resolved = api.resolve_location(location).await?;
+                    api.get_weather(&resolved).await
+                })
+                .await
+            },
+        ))
+    })?;
+
+    let mut failure = None;
+    for (location, result) in iter::zip(locations, results) {
+        show_location(location);
+        match result {
+            Ok((weather, answered_by)) => {
+                if answered_by != apis[0].provider() {
+                    println(&format!("{}: {answered_by:?}", tr(Message::AnsweredBy)));
+                }
+                show_weather(location, weather, options);
+            }
+            Err(e) => {
+                wprintln(&e.to_string());
+                failure.get_or_insert(e.exit_code());
+            }
+        }
+    }
+    if let Some(code) = failure {
+        eprintln(tr(Message::SomeLocationsFailed), code)
+    }
+    Ok(())
+}
+
+fn show_quota(storage: &Storage, quota: &Quota) {
+    let providers = storage.get_configured_providers();
+    if providers.is_empty() {
+        eprintln(tr(Message::NoProviderConfigured), sysexits::USAGE_ERR)
+    }
+
+    let budget = |calls: u32, limit: Option<u32>| match limit {
+        None => format!("{calls}, {}", tr(Message::Unlimited)),
+        Some(limit) => format!(
+            "{calls}/{limit} ({}: {})",
+            tr(Message::Remaining),
+            limit.saturating_sub(calls)
+        ),
+    };
+    for provider in providers {
+        let usage = quota.usage(provider);
+        let limits = quota.limits(provider);
+        println!(
+            "{}",
+            theme().defaults_style.apply_to(format!("{provider:?}"))
+        );
+        println(&format!(
+            "  {}: {}",
+            tr(Message::CallsToday),
+            budget(usage.daily_calls, limits.daily)
+        ));
+        println(&format!(
+            "  {}: {}",
+            tr(Message::CallsThisMinute),
+            budget(usage.minute_calls, limits.minute)
+        ));
+    }
+}
+
+fn show_providers(
+    storage: &Storage,
+    client: Option<Arc<AsyncClient>>,
+    language: Language,
+    parallel: usize,
+) -> Result<()> {
+    let providers = storage.get_configured_providers();
+    if providers.is_empty() {
+        eprintln(tr(Message::NoProviderConfigured), sysexits::USAGE_ERR)
+    }
+
+    // Check all API keys at once, so the slowest provider does not hold up the others.
+    let checks = match client {
+        None => None,
+        Some(client) => Some(with_spinner(|| {
+            block_on(api::concurrently(&providers, parallel, |&provider| {
+                let api_key = storage.get_api_key(provider).to_owned();
+                let api = api::new_async(provider, client.clone(), api_key, language);
+                async move { api.is_valid().await }
+            }))
+        })?),
+    };
+
+    for (i, &provider) in providers.iter().enumerate() {
+        let name = format!("{provider:?}");
+        if storage.get_active_provider() == Some(provider) {
+            println!(
+                "{} ({})",
+                theme().defaults_style.apply_to(name),
+                tr(Message::Active)
+            );
+        } else {
+            println!("{}", theme().defaults_style.apply_to(name));
+        }
+
+        let validity = match checks.as_ref().map(|checks| &checks[i]) {
+            None => tr(Message::NotChecked).to_string(),
+            Some(Ok(true)) => tr(Message::Valid).to_string(),
+            Some(Ok(false)) => tr(Message::Invalid).to_string(),
+            Some(Err(e)) => format!("{} ({e})", tr(Message::NotChecked)),
+        };
+        println(&format!("  {}: {validity}", tr(Message::ApiKey)));
+
+        if let Some(id) = storage.get_saved_location(provider).and_then(|l| l.id) {
+            println(&format!("  {}: {id}", tr(Message::LocationId)));
+        }
+    }
+
+    if let Some(location) = storage.get_shared_location() {
+        println(&format!("{}: {location}", tr(Message::SavedLocation)));
+    }
+    Ok(())
+}
+
+fn show_import_report(report: &ImportReport, overwrite: bool) {
+    sprintln(&format!("{}: {}", tr(Message::Imported), report.imported));
+    for provider in &report.skipped {
+        wprintln(&format!(
+            "{}: {provider:?}",
+            tr(Message::SkippedWithoutApiKey)
+        ));
+    }
+    let message = if overwrite {
+        Message::ConflictReplaced
+    } else {
+        Message::ConflictKept
+    };
+    for conflict in &report.conflicts {
+        wprintln(&format!("{}: {conflict}", tr(message)));
+    }
+    if !overwrite && !report.conflicts.is_empty() {
+        println(tr(Message::UseOverwrite));
+    }
+}
+
+fn show_locations(storage: &Storage) {
+    let locations = storage.get_locations();
+    if locations.is_empty() {
+        println(tr(Message::NoKnownLocations));
+    }
+    for location in locations {
+        println!("{}", theme().defaults_style.apply_to(location));
+    }
 }
@@ -0,0 +1,437 @@
+//! Local HTTP server exposing weather as a JSON API, so clients do not need their own provider keys.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Query, State};
+use axum::http::header::RETRY_AFTER;
+use axum::http::{Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uom::si::thermodynamic_temperature::degree_celsius;
+
+use crate::api::{self, AsyncApi};
+use crate::data::{Condition, Location, Provider, TimeZone};
+use crate::error::Error;
+
+/// Limits of the server, applied to all clients.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct Settings {
+    /// Time to keep weather and search results.
+    pub cache_ttl: Duration,
+    /// Maximum number of requests per minute from a single client, `0` means unlimited.
+    pub rate_limit: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            cache_ttl: Duration::from_secs(600),
+            rate_limit: 60,
+        }
+    }
+}
+
+/// Weather of the location as returned by `/v1/weather`.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct WeatherResponse {
+    pub location: Location,
+    pub provider: Provider,
+    /// Temperature in degrees Celsius.
+    pub temperature: f64,
+    pub condition: Condition,
+    pub is_day: bool,
+    pub description: String,
+    pub observed: Option<DateTime<Utc>>,
+    pub timezone: Option<TimeZone>,
+}
+
+/// Daily forecast of the location as returned by `/v1/forecast`.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ForecastResponse {
+    pub location: Location,
+    pub provider: Provider,
+    pub days: Vec<DayResponse>,
+}
+
+/// Expected weather of a single day, temperatures are in degrees Celsius.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct DayResponse {
+    pub date: NaiveDate,
+    pub min_temperature: f64,
+    pub max_temperature: f64,
+    pub condition: Condition,
+    pub description: String,
+}
+
+/// Providers in failover order along with known locations, shared by all requests.
+pub struct Server {
+    apis: Vec<Box<dyn AsyncApi>>,
+    locations: Vec<Location>,
+    settings: Settings,
+    cache: Mutex<HashMap<String, (Instant, Value)>>,
+    clients: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl Server {
+    pub fn new(apis: Vec<Box<dyn AsyncApi>>, locations: Vec<Location>, settings: Settings) -> Self {
+        assert!(!apis.is_empty(), "at least one provider should be given");
+        Self {
+            apis,
+            locations,
+            settings,
+            cache: Mutex::new(HashMap::new()),
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Serve requests until the process is stopped, the runtime should have IO and time enabled.
+    pub async fn serve(self, listener: TcpListener) -> io::Result<()> {
+        info!("listening on {}", listener.local_addr()?);
+        let app = self.router();
+        axum::Server::from_tcp(listener)
+            .map_err(io::Error::other)?
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .map_err(io::Error::other)
+    }
+
+    fn router(self) -> Router {
+        let server = Arc::new(self);
+        let api = Router::new()
+            .route("/weather", get(weather))
+            .route("/forecast", get(forecast))
+            .route("/locations/search", get(search))
+            .route_layer(middleware::from_fn_with_state(server.clone(), rate_limit))
+            .with_state(server);
+        Router::new()
+            .route(
+                "/healthz",
+                get(|| async { Json(json!({ "status": "ok" })) }),
+            )
+            .nest("/v1", api)
+    }
+
+    /// Take the answer from the cache or compute and keep it for a while.
+    async fn cached<T: Serialize>(
+        &self,
+        key: String,
+        f: impl std::future::Future<Output = Result<T, Error>>,
+    ) -> Result<Value, Error> {
+        if let Some((at, value)) = self
+            .cache
+            .lock()
+            .expect("lock should not be poisoned")
+            .get(&key)
+        {
+            if at.elapsed() < self.settings.cache_ttl {
+                debug!("cache hit for \"{key}\"");
+                return Ok(value.clone());
+            }
+        }
+        let value = serde_json::to_value(f.await?).expect("response should be serializable");
+        let mut cache = self.cache.lock().expect("lock should not be poisoned");
+        let ttl = self.settings.cache_ttl;
+        cache.retain(|_, (at, _)| at.elapsed() < ttl);
+        cache.insert(key, (Instant::now(), value.clone()));
+        Ok(value)
+    }
+
+    /// Known location, coordinates or the top match of the search.
+    async fn resolve(&self, query: &str) -> Result<Location, Error> {
+        if let Some(location) = self
+            .locations
+            .iter()
+            .find(|l| l.name.eq_ignore_ascii_case(query))
+        {
+            return Ok(location.clone());
+        }
+        if let Some(location) = Location::from_coordinates(query) {
+            return Ok(location);
+        }
+        let (locations, _) =
+            api::with_failover_async(&self.apis, |api| api.search_location(query)).await?;
+        locations.into_iter().next().ok_or(Error::LocationNotFound)
+    }
+
+    async fn weather(&self, query: &str) -> Result<WeatherResponse, Error> {
+        let location = self.resolve(query).await?;
+        let ((weather, location), provider) = api::with_failover_async(&self.apis, |api| {
+            let location = &location;
+            async move {
+                let resolved = api.resolve_location(location).await?;
+                Ok((api.get_weather(&resolved).await?, resolved))
+            }
+        })
+        .await?;
+        Ok(WeatherResponse {
+            location,
+            provider,
+            temperature: weather.temperature.get::<degree_celsius>(),
+            condition: weather.condition,
+            is_day: weather.is_day,
+            description: weather.description,
+            observed: weather.observed,
+            timezone: weather.timezone,
+        })
+    }
+
+    async fn forecast(&self, query: &str) -> Result<ForecastResponse, Error> {
+        let location = self.resolve(query).await?;
+        let ((days, location), provider) = api::with_failover_async(&self.apis, |api| {
+            let location = &location;
+            async move {
+                let resolved = api.resolve_location(location).await?;
+                Ok((api.get_forecast(&resolved).await?, resolved))
+            }
+        })
+        .await?;
+        Ok(ForecastResponse {
+            location,
+            provider,
+            days: days
+                .into_iter()
+                .map(|day| DayResponse {
+                    date: day.date,
+                    min_temperature: day.min_temperature.get::<degree_celsius>(),
+                    max_temperature: day.max_temperature.get::<degree_celsius>(),
+                    condition: day.condition,
+                    description: day.description,
+                })
+                .collect(),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct WeatherQuery {
+    location: Option<String>,
+}
+
+async fn weather(State(server): State<Arc<Server>>, Query(query): Query<WeatherQuery>) -> Response {
+    let Some(location) = query.location.filter(|l| !l.trim().is_empty()) else {
+        return failure(
+            StatusCode::BAD_REQUEST,
+            "\"location\" parameter is required",
+        );
+    };
+    let key = format!("weather:{}", location.trim().to_lowercase());
+    respond(server.cached(key, server.weather(location.trim())).await)
+}
+
+async fn forecast(
+    State(server): State<Arc<Server>>,
+    Query(query): Query<WeatherQuery>,
+) -> Response {
+    let Some(location) = query.location.filter(|l| !l.trim().is_empty()) else {
+        return failure(
+            StatusCode::BAD_REQUEST,
+            "\"location\" parameter is required",
+        );
+    };
+    let key = format!("forecast:{}", location.trim().to_lowercase());
+    respond(server.cached(key, server.forecast(location.trim())).await)
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: Option<String>,
+}
+
+async fn search(State(server): State<Arc<Server>>, Query(query): Query<SearchQuery>) -> Response {
+    let Some(q) = query.q.filter(|q| !q.trim().is_empty()) else {
+        return failure(StatusCode::BAD_REQUEST, "\"q\" parameter is required");
+    };
+    let key = format!("search:{}", q.trim().to_lowercase());
+    let locations = async {
+        let apis = &server.apis;
+        let (locations, _) =
+            api::with_failover_async(apis, |api| api.search_location(q.trim())).await?;
+        Ok(locations)
+    };
+    respond(server.cached(key, locations).await)
+}
+
+/// Allow a number of requests per minute from every client address.
+async fn rate_limit(
+    State(server): State<Arc<Server>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    let limit = server.settings.rate_limit;
+    if limit > 0 {
+        let mut clients = server.clients.lock().expect("lock should not be poisoned");
+        clients.retain(|_, (start, _)| start.elapsed() < WINDOW);
+        let (start, calls) = clients.entry(addr.ip()).or_insert((Instant::now(), 0));
+        if *calls >= limit {
+            let retry_after = WINDOW.saturating_sub(start.elapsed()).as_secs() + 1;
+            let mut response = failure(StatusCode::TOO_MANY_REQUESTS, "rate limit is exceeded");
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, retry_after.into());
+            return response;
+        }
+        *calls += 1;
+    }
+    next.run(request).await
+}
+
+fn respond(result: Result<Value, Error>) -> Response {
+    match result {
+        Ok(value) => Json(value).into_response(),
+        Err(e) => failure(status(&e), &e.to_string()),
+    }
+}
+
+fn failure(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+/// Status code telling the client whether the problem is on its side, ours or the provider's.
+fn status(error: &Error) -> StatusCode {
+    match error {
+        Error::LocationNotFound => StatusCode::NOT_FOUND,
+        Error::QuotaExceeded | Error::BudgetExceeded { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        Error::InvalidApiKey
+        | Error::ProviderOutage(_)
+        | Error::UnexpectedStatus(_)
+        | Error::Network(_)
+        | Error::MalformedResponse { .. } => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::api::stub::{location, Stub};
+
+    use super::*;
+
+    /// Start the server with the stub provider and run the test against its url.
+    fn with_server<F: Future<Output = ()>>(
+        settings: Settings,
+        test: impl FnOnce(String, Arc<AtomicUsize>) -> F,
+    ) {
+        // Provider that knows only Kyiv.
+        let stub = Stub::new(Provider::OpenWeather).knowing(vec![location("Kyiv", 50.45, 30.52)]);
+        let calls = stub.calls.clone();
+        let apis: Vec<Box<dyn AsyncApi>> = vec![Box::new(stub)];
+        let office = Location::from_coordinates("49.84,24.03").map(|l| Location {
+            name: "Office".to_string(),
+            ..l
+        });
+        let server = Server::new(apis, office.into_iter().collect(), settings);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async {
+                tokio::spawn(server.serve(listener));
+                test(url, calls).await
+            });
+    }
+
+    async fn call(url: &str) -> (StatusCode, Value) {
+        let response = reqwest::get(url).await.unwrap();
+        let status = StatusCode::from_u16(response.status().as_u16()).unwrap();
+        (status, response.json().await.unwrap())
+    }
+
+    #[test]
+    fn serve_weather() {
+        with_server(Settings::default(), |url, calls| async move {
+            let (status, body) = call(&format!("{url}/healthz")).await;
+            assert_eq!((status, body), (StatusCode::OK, json!({ "status": "ok" })));
+
+            // Searched, known and given by coordinates locations.
+            for (query, temperature) in [("Kyiv", 50.45), ("office", 49.84), ("10,20", 10.0)] {
+                let (status, body) = call(&format!("{url}/v1/weather?location={query}")).await;
+                assert_eq!(status, StatusCode::OK);
+                let weather: WeatherResponse = serde_json::from_value(body).unwrap();
+                assert_eq!(weather.provider, Provider::OpenWeather);
+                assert!((weather.temperature - temperature).abs() < 1e-9);
+            }
+            assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+            // Answers are cached, regardless of the case.
+            call(&format!("{url}/v1/weather?location=KYIV")).await;
+            assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+            let (status, body) = call(&format!("{url}/v1/locations/search?q=Kyiv")).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body[0]["name"], "Kyiv");
+
+            let (status, body) = call(&format!("{url}/v1/weather?location=Atlantis")).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+            assert_eq!(body["error"], Error::LocationNotFound.to_string());
+
+            let (status, _) = call(&format!("{url}/v1/weather")).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        });
+    }
+
+    #[test]
+    fn serve_forecast() {
+        with_server(Settings::default(), |url, calls| async move {
+            let (status, body) = call(&format!("{url}/v1/forecast?location=office")).await;
+            assert_eq!(status, StatusCode::OK);
+            let forecast: ForecastResponse = serde_json::from_value(body).unwrap();
+            assert_eq!(forecast.provider, Provider::OpenWeather);
+            assert_eq!(forecast.location.name, "Office");
+            assert_eq!(forecast.days.len(), 3);
+            assert!((forecast.days[0].min_temperature - 44.84).abs() < 1e-9);
+            assert!((forecast.days[0].max_temperature - 54.84).abs() < 1e-9);
+
+            // Forecasts are cached apart from the weather.
+            call(&format!("{url}/v1/forecast?location=Office")).await;
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+            call(&format!("{url}/v1/weather?location=office")).await;
+            assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+            let (status, _) = call(&format!("{url}/v1/forecast?location=Atlantis")).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+
+            let (status, _) = call(&format!("{url}/v1/forecast")).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        });
+    }
+
+    #[test]
+    fn limit_requests_per_client() {
+        let settings = Settings {
+            rate_limit: 2,
+            ..Default::default()
+        };
+        with_server(settings, |url, _| async move {
+            for _ in 0..2 {
+                let (status, _) = call(&format!("{url}/v1/weather?location=Kyiv")).await;
+                assert_eq!(status, StatusCode::OK);
+            }
+            let (status, body) = call(&format!("{url}/v1/weather?location=Kyiv")).await;
+            assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(body["error"], "rate limit is exceeded");
+
+            // Health checks are not limited.
+            let (status, _) = call(&format!("{url}/healthz")).await;
+            assert_eq!(status, StatusCode::OK);
+        });
+    }
+}
@@ -0,0 +1,586 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::alert::Alert;
+use crate::api::ClientSettings;
+use crate::data::{Language, Location, Provider};
+use crate::error::{Error, Result};
+
+use super::{replace_proxy_password, DaemonSettings, ProviderData, Storage};
+
+/// Version of the export format, bumped on any incompatible change.
+const FORMAT_VERSION: u32 = 1;
+
+/// Coordinates closer than this, in degrees, are treated as the same place.
+const SAME_PLACE: f64 = 0.01;
+
+/// Portable snapshot of the config, with API keys and proxy credentials only when asked.
+// NOTE: Order of fields does matter.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Export {
+    version: u32,
+    active_provider: Option<Provider>,
+    language: Option<Language>,
+    #[serde(default)]
+    fallback_providers: Vec<Provider>,
+    stale_after: Option<u64>,
+    #[serde(default)]
+    providers: Vec<ExportedProvider>,
+    location: Option<Location>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    locations: Vec<Location>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    alerts: Vec<Alert>,
+    http: Option<ClientSettings>,
+    daemon: Option<DaemonSettings>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct ExportedProvider {
+    kind: Provider,
+    api_key: Option<String>,
+    daily_limit: Option<u32>,
+    minute_limit: Option<u32>,
+}
+
+/// Imported value that differs from the current one.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Conflict {
+    ApiKey(Provider),
+    Limits(Provider),
+    ActiveProvider,
+    Language,
+    FallbackProviders,
+    StaleAfter,
+    Location,
+    KnownLocation(String),
+    Alert(String),
+    Http,
+    Daemon,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Conflict::ApiKey(kind) => write!(f, "API key of {kind:?} provider"),
+            Conflict::Limits(kind) => write!(f, "limits of {kind:?} provider"),
+            Conflict::ActiveProvider => write!(f, "active provider"),
+            Conflict::Language => write!(f, "language"),
+            Conflict::FallbackProviders => write!(f, "fallback providers"),
+            Conflict::StaleAfter => write!(f, "age of stale weather"),
+            Conflict::Location => write!(f, "saved location"),
+            Conflict::KnownLocation(name) => write!(f, "location \"{name}\""),
+            Conflict::Alert(name) => write!(f, "alert \"{name}\""),
+            Conflict::Http => write!(f, "network settings"),
+            Conflict::Daemon => write!(f, "daemon schedule"),
+        }
+    }
+}
+
+/// Outcome of an import.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Number of values added or replaced.
+    pub imported: usize,
+    /// Values that differ, kept or replaced depending on the overwrite flag.
+    pub conflicts: Vec<Conflict>,
+    /// Providers that cannot be configured, as the export has no API keys.
+    pub skipped: Vec<Provider>,
+}
+
+impl ImportReport {
+    /// Take the imported value if there is no current one, or on conflict if asked to overwrite.
+    fn merge<T: PartialEq>(
+        &mut self,
+        current: &mut T,
+        imported: T,
+        is_unset: bool,
+        overwrite: bool,
+        conflict: Conflict,
+    ) {
+        if *current == imported {
+            return;
+        }
+        if !is_unset {
+            self.conflicts.push(conflict);
+            if !overwrite {
+                return;
+            }
+        }
+        *current = imported;
+        self.imported += 1;
+    }
+}
+
+impl Storage {
+    pub fn export(&self, include_secrets: bool) -> Export {
+        let config = &self.config;
+        let mut http = config.http.clone();
+        if !include_secrets {
+            replace_proxy_password(&mut http, None);
+        }
+        Export {
+            version: FORMAT_VERSION,
+            active_provider: config.active_provider,
+            language: config.language,
+            fallback_providers: config.fallback_providers.clone(),
+            stale_after: config.stale_after,
+            providers: config
+                .providers
+                .iter()
+                .map(|p| ExportedProvider {
+                    kind: p.kind,
+                    api_key: include_secrets.then(|| p.api_key.clone()),
+                    daily_limit: p.daily_limit,
+                    minute_limit: p.minute_limit,
+                })
+                .collect(),
+            location: config.location.clone(),
+            locations: config.locations.clone(),
+            alerts: config.alerts.clone(),
+            http: (http != ClientSettings::default()).then_some(http),
+            daemon: (config.daemon != DaemonSettings::default()).then(|| config.daemon.clone()),
+        }
+    }
+
+    /// Merge the export into the config, keeping current values on conflicts unless asked to overwrite.
+    pub fn import(&mut self, export: Export, overwrite: bool) -> Result<ImportReport> {
+        if export.version != FORMAT_VERSION {
+            return Err(Error::InvalidImport(format!(
+                "export version {} is not supported, expected {FORMAT_VERSION}",
+                export.version
+            )));
+        }
+
+        let mut report = ImportReport::default();
+        for imported in export.providers {
+            let kind = imported.kind;
+            let limits = (imported.daily_limit, imported.minute_limit);
+            match self.config.providers.iter_mut().find(|p| p.kind == kind) {
+                Some(current) => {
+                    if let Some(api_key) = imported.api_key {
+                        let conflict = Conflict::ApiKey(kind);
+                        report.merge(&mut current.api_key, api_key, false, overwrite, conflict);
+                    }
+                    let mut current_limits = (current.daily_limit, current.minute_limit);
+                    let is_unset = current_limits == (None, None);
+                    let conflict = Conflict::Limits(kind);
+                    report.merge(&mut current_limits, limits, is_unset, overwrite, conflict);
+                    (current.daily_limit, current.minute_limit) = current_limits;
+                }
+                None => match imported.api_key {
+                    Some(api_key) => {
+                        self.config.providers.push(ProviderData {
+                            kind,
+                            api_key,
+                            daily_limit: limits.0,
+                            minute_limit: limits.1,
+                            location_id: None,
+                        });
+                        report.imported += 1;
+                    }
+                    None => report.skipped.push(kind),
+                },
+            }
+        }
+
+        let config = &mut self.config;
+        let providers = &config.providers;
+        let is_configured = |kind: &Provider| providers.iter().any(|p| p.kind == *kind);
+        let active_provider = export.active_provider.filter(is_configured);
+        let mut fallback_providers = export.fallback_providers;
+        fallback_providers.retain(is_configured);
+        if active_provider.is_some() {
+            let is_unset = config.active_provider.is_none();
+            let conflict = Conflict::ActiveProvider;
+            report.merge(
+                &mut config.active_provider,
+                active_provider,
+                is_unset,
+                overwrite,
+                conflict,
+            );
+        }
+        if export.language.is_some() {
+            let is_unset = config.language.is_none();
+            let conflict = Conflict::Language;
+            report.merge(
+                &mut config.language,
+                export.language,
+                is_unset,
+                overwrite,
+                conflict,
+            );
+        }
+        if !fallback_providers.is_empty() {
+            let is_unset = config.fallback_providers.is_empty();
+            let conflict = Conflict::FallbackProviders;
+            report.merge(
+                &mut config.fallback_providers,
+                fallback_providers,
+                is_unset,
+                overwrite,
+                conflict,
+            );
+        }
+        if export.stale_after.is_some() {
+            let is_unset = config.stale_after.is_none();
+            let conflict = Conflict::StaleAfter;
+            report.merge(
+                &mut config.stale_after,
+                export.stale_after,
+                is_unset,
+                overwrite,
+                conflict,
+            );
+        }
+        if let Some(http) = export.http {
+            let is_unset = config.http == ClientSettings::default();
+            report.merge(&mut config.http, http, is_unset, overwrite, Conflict::Http);
+        }
+        if let Some(daemon) = export.daemon {
+            let is_unset = config.daemon == DaemonSettings::default();
+            report.merge(
+                &mut config.daemon,
+                daemon,
+                is_unset,
+                overwrite,
+                Conflict::Daemon,
+            );
+        }
+        // Alerts are told apart by name, like known sites.
+        for alert in export.alerts {
+            match config.alerts.iter_mut().find(|a| a.name == alert.name) {
+                None => {
+                    config.alerts.push(alert);
+                    report.imported += 1;
+                }
+                Some(current) => {
+                    let conflict = Conflict::Alert(current.name.clone());
+                    report.merge(current, alert, false, overwrite, conflict);
+                }
+            }
+        }
+        if let Some(location) = export.location {
+            let is_same_place = config
+                .location
+                .as_ref()
+                .is_some_and(|l| is_same(l, &location));
+            if !is_same_place {
+                let is_unset = config.location.is_none();
+                if !is_unset {
+                    report.conflicts.push(Conflict::Location);
+                }
+                if is_unset || overwrite {
+                    config.location = Some(location);
+                    // Identifiers belong to the previous location.
+                    for provider in &mut config.providers {
+                        provider.location_id = None;
+                    }
+                    report.imported += 1;
+                }
+            }
+        }
+        self.merge_locations(export.locations, overwrite, &mut report);
+
+        self.changed |= report.imported > 0;
+        Ok(report)
+    }
+
+    /// Add known sites, a site with the same name but in another place is a conflict.
+    pub fn import_locations(&mut self, locations: Vec<Location>, overwrite: bool) -> ImportReport {
+        let mut report = ImportReport::default();
+        self.merge_locations(locations, overwrite, &mut report);
+        self.changed |= report.imported > 0;
+        report
+    }
+
+    fn merge_locations(
+        &mut self,
+        locations: Vec<Location>,
+        overwrite: bool,
+        report: &mut ImportReport,
+    ) {
+        for mut location in locations {
+            // Identifiers are provider-specific, only coordinates are portable.
+            location.id = None;
+            let known = &mut self.config.locations;
+            match known
+                .iter_mut()
+                .find(|l| l.name.eq_ignore_ascii_case(&location.name))
+            {
+                None => {
+                    known.push(location);
+                    report.imported += 1;
+                }
+                Some(current) if !is_same(current, &location) => {
+                    let conflict = Conflict::KnownLocation(current.name.clone());
+                    report.merge(current, location, false, overwrite, conflict);
+                }
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+fn is_same(location: &Location, other: &Location) -> bool {
+    let near = |a: Option<f64>, b: Option<f64>| match (a, b) {
+        (Some(a), Some(b)) => (a - b).abs() < SAME_PLACE,
+        (a, b) => a.is_none() && b.is_none(),
+    };
+    location.name.eq_ignore_ascii_case(&other.name)
+        && near(location.lat, other.lat)
+        && near(location.lon, other.lon)
+}
+
+#[derive(Deserialize)]
+struct CsvLocation {
+    name: String,
+    lat: f64,
+    lon: f64,
+    #[serde(default)]
+    state: Option<String>,
+    #[serde(default)]
+    country: String,
+}
+
+/// Parse locations from CSV with a header, "name", "lat" and "lon" columns are required,
+/// "state" and "country" are optional.
+pub fn parse_csv(content: &str) -> Result<Vec<Location>> {
+    csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes())
+        .deserialize()
+        .map(|record| {
+            let record: CsvLocation = record.map_err(|e| Error::InvalidImport(e.to_string()))?;
+            Ok(Location {
+                id: None,
+                name: record.name,
+                state: record.state.filter(|s| !s.is_empty()),
+                country: record.country,
+                lat: Some(record.lat),
+                lon: Some(record.lon),
+                timezone: None,
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct FeatureCollection {
+    features: Vec<Feature>,
+}
+
+#[derive(Deserialize)]
+struct Feature {
+    geometry: Point,
+    properties: Properties,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum Point {
+    // Positions can have altitude and more values after longitude and latitude.
+    Point { coordinates: Vec<f64> },
+}
+
+#[derive(Deserialize)]
+struct Properties {
+    name: String,
+    #[serde(default)]
+    state: Option<String>,
+    #[serde(default)]
+    country: String,
+}
+
+/// Parse locations from a GeoJSON feature collection of points with a "name" property.
+pub fn parse_geojson(content: &str) -> Result<Vec<Location>> {
+    let de = &mut serde_json::Deserializer::from_str(content);
+    let collection: FeatureCollection = serde_path_to_error::deserialize(de)
+        .map_err(|e| Error::InvalidImport(format!("at \"{}\": {}", e.path(), e.inner())))?;
+    collection
+        .features
+        .into_iter()
+        .enumerate()
+        .map(|(i, feature)| {
+            // GeoJSON puts longitude first.
+            let Point::Point { coordinates } = feature.geometry;
+            let [lon, lat, ..] = coordinates[..] else {
+                return Err(Error::InvalidImport(format!(
+                    "at \"features[{i}].geometry.coordinates\": expected longitude and latitude"
+                )));
+            };
+            Ok(Location {
+                id: None,
+                name: feature.properties.name,
+                state: feature.properties.state,
+                country: feature.properties.country,
+                lat: Some(lat),
+                lon: Some(lon),
+                timezone: None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use assert_fs::NamedTempFile;
+
+    use crate::api::stub::location;
+    use crate::data::Provider::{AccuWeather, OpenWeather};
+
+    use super::*;
+
+    fn alert(name: &str, rule: &str) -> Alert {
+        toml::from_str(&format!("name = \"{name}\"\nrule = \"{rule}\"\n")).unwrap()
+    }
+
+    #[test]
+    fn export_and_import() {
+        let config = NamedTempFile::new("config.toml").unwrap();
+        let mut storage = Storage::load(Some(config.path())).unwrap();
+        storage.configure_provider(OpenWeather, "api_key".into());
+        storage.set_language(Language::Uk);
+        storage.save_location(OpenWeather, location("Kyiv", 50.45, 30.52));
+        storage.import_locations(vec![location("Office", 49.84, 24.03)], false);
+        storage.config.stale_after = Some(600);
+        storage.config.alerts = vec![alert("frost", "temp < -5"), alert("storm", "wind > 15")];
+        storage.config.daemon.interval = 300;
+
+        let export = storage.export(false);
+        assert_eq!(export.providers[0].api_key, None);
+        let text = toml::to_string(&export).unwrap();
+        assert!(!text.contains("api_key"));
+
+        // Without keys providers cannot be configured.
+        let other = NamedTempFile::new("other.toml").unwrap();
+        let mut other = Storage::load(Some(other.path())).unwrap();
+        other.configure_provider(AccuWeather, "other_key".into());
+        let report = other.import(toml::from_str(&text).unwrap(), false).unwrap();
+        assert_eq!(report.skipped, vec![OpenWeather]);
+        assert!(report.conflicts.is_empty());
+        assert_eq!(other.get_active_provider(), Some(AccuWeather));
+        assert_eq!(other.get_language(), Some(Language::Uk));
+        assert_eq!(other.get_shared_location().unwrap().name, "Kyiv");
+        assert_eq!(other.get_locations()[0].name, "Office");
+        assert_eq!(other.get_stale_after(), Duration::from_secs(600));
+        assert_eq!(other.get_alerts(), storage.get_alerts());
+        assert_eq!(other.get_daemon_settings().interval, 300);
+
+        // Changed values are kept unless asked to overwrite.
+        storage.config.stale_after = Some(900);
+        storage.config.alerts[0] = alert("frost", "temp < -10");
+        storage.config.daemon.interval = 120;
+        let report = other.import(storage.export(false), false).unwrap();
+        assert_eq!(
+            report.conflicts,
+            vec![
+                Conflict::StaleAfter,
+                Conflict::Daemon,
+                Conflict::Alert("frost".into())
+            ]
+        );
+        assert_eq!(other.get_stale_after(), Duration::from_secs(600));
+        assert_eq!(other.get_alerts()[0], alert("frost", "temp < -5"));
+        assert_eq!(other.get_daemon_settings().interval, 300);
+
+        // With keys they can, and conflicts are resolved as asked.
+        let export = storage.export(true);
+        assert_eq!(export.providers[0].api_key.as_deref(), Some("api_key"));
+        let report = other.import(export, true).unwrap();
+        assert!(report.skipped.is_empty());
+        assert_eq!(report.conflicts[0], Conflict::ActiveProvider);
+        assert_eq!(report.conflicts.len(), 4);
+        assert_eq!(other.get_active_provider(), Some(OpenWeather));
+        assert_eq!(other.get_api_key(OpenWeather), "api_key");
+        assert_eq!(other.get_stale_after(), Duration::from_secs(900));
+        assert_eq!(other.get_alerts(), storage.get_alerts());
+        assert_eq!(other.get_daemon_settings().interval, 120);
+    }
+
+    #[test]
+    fn import_known_locations() {
+        let config = NamedTempFile::new("config.toml").unwrap();
+        let mut storage = Storage::load(Some(config.path())).unwrap();
+        let report = storage.import_locations(
+            vec![
+                location("Office", 49.84, 24.03),
+                location("Depot", 50.0, 30.0),
+            ],
+            false,
+        );
+        assert_eq!(report.imported, 2);
+
+        let report = storage.import_locations(
+            vec![
+                location("office", 49.841, 24.031),
+                location("Depot", 46.48, 30.72),
+            ],
+            false,
+        );
+        assert_eq!(report.imported, 0);
+        assert_eq!(
+            report.conflicts,
+            vec![Conflict::KnownLocation("Depot".into())]
+        );
+        assert_eq!(storage.find_location("depot").unwrap().lat, Some(50.0));
+
+        storage.import_locations(vec![location("Depot", 46.48, 30.72)], true);
+        assert_eq!(storage.find_location("depot").unwrap().lat, Some(46.48));
+    }
+
+    #[test]
+    fn parse_location_files() {
+        let locations = parse_csv("name, lat, lon, country\nOffice, 49.84, 24.03, UA\n").unwrap();
+        assert_eq!(locations, vec![location("Office", 49.84, 24.03)]);
+
+        let error = parse_csv("name,lat,lon\nOffice,north,24.03\n").unwrap_err();
+        assert!(error.to_string().contains("line: 2"), "{error}");
+
+        let locations = parse_geojson(
+            r#"{
+                "type": "FeatureCollection",
+                "features": [{
+                    "type": "Feature",
+                    "geometry": {"type": "Point", "coordinates": [24.03, 49.84]},
+                    "properties": {"name": "Office", "country": "UA"}
+                }, {
+                    "type": "Feature",
+                    "geometry": {"type": "Point", "coordinates": [30.52, 50.45, 179.0]},
+                    "properties": {"name": "Kyiv", "country": "UA"}
+                }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            locations,
+            vec![
+                location("Office", 49.84, 24.03),
+                location("Kyiv", 50.45, 30.52)
+            ]
+        );
+
+        let error = parse_geojson(
+            r#"{"features": [{"geometry": {"type": "Point", "coordinates": [24.03]},
+                "properties": {"name": "Office"}}]}"#,
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("features[0].geometry.coordinates"),
+            "{error}"
+        );
+
+        let error =
+            parse_geojson(r#"{"features": [{"geometry": {"type": "LineString"}}]}"#).unwrap_err();
+        assert!(
+            error.to_string().contains("features[0].geometry"),
+            "{error}"
+        );
+    }
+}
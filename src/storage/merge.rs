@@ -0,0 +1,139 @@
+use toml::value::{Array, Table};
+use toml::Value;
+
+/// Three-way merge of configs: what changed in `ours` since `base` wins, the rest comes from `theirs`.
+pub fn merge(base: &Value, ours: &Value, theirs: &Value) -> Value {
+    merge_option(Some(base), Some(ours), Some(theirs)).unwrap_or_else(|| ours.clone())
+}
+
+fn merge_option(
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+) -> Option<Value> {
+    if ours == base {
+        return theirs.cloned();
+    }
+    if theirs == base || theirs == ours {
+        return ours.cloned();
+    }
+    let empty = Table::new();
+    match (ours?, theirs) {
+        (Value::Table(ours), Some(Value::Table(theirs))) => {
+            let base = base.and_then(Value::as_table).unwrap_or(&empty);
+            Some(Value::Table(merge_tables(base, ours, theirs)))
+        }
+        (Value::Array(ours), Some(Value::Array(theirs))) if is_keyed(ours) && is_keyed(theirs) => {
+            let base = base
+                .and_then(Value::as_array)
+                .map_or(&[][..], Vec::as_slice);
+            Some(Value::Array(merge_keyed(base, ours, theirs)))
+        }
+        // Both sides changed the same value, ours is the latest.
+        (ours, _) => Some(ours.clone()),
+    }
+}
+
+fn merge_tables(base: &Table, ours: &Table, theirs: &Table) -> Table {
+    let mut keys: Vec<_> = theirs.keys().chain(ours.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter_map(|key| {
+            let value = merge_option(base.get(key), ours.get(key), theirs.get(key))?;
+            Some((key.clone(), value))
+        })
+        .collect()
+}
+
+/// Arrays of tables identified by "kind", like providers, are merged per element.
+fn is_keyed(array: &Array) -> bool {
+    array.iter().all(|value| key(value).is_some())
+}
+
+fn key(value: &Value) -> Option<&str> {
+    value.get("kind")?.as_str()
+}
+
+fn find<'a>(array: &'a [Value], kind: &str) -> Option<&'a Value> {
+    array.iter().find(|value| key(value) == Some(kind))
+}
+
+fn merge_keyed(base: &[Value], ours: &[Value], theirs: &[Value]) -> Array {
+    let kinds: Vec<_> = theirs.iter().chain(ours).filter_map(key).collect();
+    let mut merged = Array::new();
+    for (i, kind) in kinds.iter().enumerate() {
+        if kinds[..i].contains(kind) {
+            continue;
+        }
+        if let Some(value) = merge_option(find(base, kind), find(ours, kind), find(theirs, kind)) {
+            merged.push(value);
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge_str(base: &str, ours: &str, theirs: &str) -> Value {
+        let parse = |s: &str| toml::from_str::<Value>(s).unwrap();
+        merge(&parse(base), &parse(ours), &parse(theirs))
+    }
+
+    #[test]
+    fn merge_changes() {
+        let cases = [
+            // Only ours changed.
+            ("a = 1", "a = 2", "a = 1", "a = 2"),
+            // Only theirs changed.
+            ("a = 1", "a = 1", "a = 3", "a = 3"),
+            // Different fields changed.
+            (
+                "a = 1\nb = 1",
+                "a = 2\nb = 1",
+                "a = 1\nb = 3",
+                "a = 2\nb = 3",
+            ),
+            // Same field changed, ours is the latest.
+            ("a = 1", "a = 2", "a = 3", "a = 2"),
+            // Field removed by ours or added by theirs.
+            (
+                "a = 1\nb = 1",
+                "b = 1",
+                "a = 1\nb = 1\nc = 1",
+                "b = 1\nc = 1",
+            ),
+            // Nested tables are merged too.
+            (
+                "[t]\na = 1\nb = 1",
+                "[t]\na = 2\nb = 1",
+                "[t]\na = 1\nb = 3",
+                "[t]\na = 2\nb = 3",
+            ),
+            // Elements with kind are merged per kind.
+            (
+                "[[p]]\nkind = \"A\"\nv = 1",
+                "[[p]]\nkind = \"A\"\nv = 2",
+                "[[p]]\nkind = \"A\"\nv = 1\n[[p]]\nkind = \"B\"",
+                "[[p]]\nkind = \"A\"\nv = 2\n[[p]]\nkind = \"B\"",
+            ),
+            (
+                "[[p]]\nkind = \"A\"\n[[p]]\nkind = \"B\"",
+                "[[p]]\nkind = \"B\"",
+                "[[p]]\nkind = \"A\"",
+                "p = []",
+            ),
+            // Plain arrays are replaced as a whole.
+            ("a = [1]", "a = [1, 2]", "a = [3]", "a = [1, 2]"),
+        ];
+        for (base, ours, theirs, expected) in cases {
+            assert_eq!(
+                merge_str(base, ours, theirs),
+                toml::from_str::<Value>(expected).unwrap(),
+                "base: {base:?}, ours: {ours:?}, theirs: {theirs:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use log::debug;
+use toml::value::Table;
+use toml::Value;
+
+use crate::error::{Error, Result};
+
+/// Version of the config layout written by this build.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// Upgrade of the config layout from some version to the next one.
+type Migration = fn(&mut Table);
+
+/// Migrations in order, the first one upgrades version 1.
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize - 1] = [move_saved_locations];
+
+/// Upgrade config of any older version to the current one step by step.
+/// Return the version the config had.
+pub fn migrate(config: &mut Value) -> Result<u32> {
+    let Some(config) = config.as_table_mut() else {
+        return Ok(CURRENT_VERSION);
+    };
+    let version = match config.get("version") {
+        // Version 1 had no version field.
+        None => 1,
+        Some(Value::Integer(version)) => match u32::try_from(*version) {
+            Ok(version @ 1..=CURRENT_VERSION) => version,
+            _ => {
+                return Err(Error::UnsupportedConfigVersion {
+                    version: *version,
+                    supported: CURRENT_VERSION,
+                })
+            }
+        },
+        // Let deserialization point to the mistake.
+        Some(_) => return Ok(CURRENT_VERSION),
+    };
+
+    for (from, migration) in (version..).zip(&MIGRATIONS[version as usize - 1..]) {
+        migration(config);
+        debug!("migrated config from version {from} to {}", from + 1);
+    }
+    config.insert(
+        "version".to_string(),
+        Value::Integer(CURRENT_VERSION.into()),
+    );
+    Ok(version)
+}
+
+/// Version 1 kept a location per provider, move the active provider's one into the shared location,
+/// keeping its identifier for that provider only.
+fn move_saved_locations(config: &mut Table) {
+    let active = config
+        .get("active_provider")
+        .and_then(Value::as_str)
+        .map(str::to_owned);
+    let Some(providers) = config.get_mut("providers").and_then(Value::as_array_mut) else {
+        return;
+    };
+
+    let has_location = |provider: &&Value| provider.get("saved_location").is_some();
+    let is_active =
+        |provider: &&Value| provider.get("kind").and_then(Value::as_str) == active.as_deref();
+    let chosen = providers
+        .iter()
+        .position(|p| has_location(&p) && is_active(&p))
+        .or_else(|| providers.iter().position(|p| has_location(&p)));
+
+    let mut shared = None;
+    for (i, provider) in providers.iter_mut().enumerate() {
+        let Some(provider) = provider.as_table_mut() else {
+            continue;
+        };
+        let Some(Value::Table(mut location)) = provider.remove("saved_location") else {
+            continue;
+        };
+        if Some(i) == chosen {
+            // Identifier is valid only for the provider that found the location.
+            if let Some(id) = location.remove("id") {
+                provider.insert("location_id".to_string(), id);
+            }
+            shared = Some(location);
+        }
+    }
+
+    if let Some(location) = shared {
+        config
+            .entry("location".to_string())
+            .or_insert(Value::Table(location));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::Language;
+    use crate::data::Provider::{AccuWeather, OpenWeather, WeatherApi};
+
+    use super::super::{parse, Config};
+    use super::*;
+
+    fn parse_fixture(name: &str) -> (Config, u32) {
+        let path = format!(
+            "{}/tests/fixtures/config/{name}",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        parse(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn migrate_baseline_layout() {
+        let (config, version) = parse_fixture("v1-baseline.toml");
+        assert_eq!(version, 1);
+        assert_eq!(config.version, CURRENT_VERSION);
+        assert_eq!(config.active_provider, Some(AccuWeather));
+
+        // Location of the active provider wins.
+        let location = config.location.unwrap();
+        assert_eq!(location.name, "Kyiv");
+        assert_eq!(location.id, None);
+        assert_eq!((location.lat, location.lon), (None, None));
+        assert_eq!(config.providers[0].kind, OpenWeather);
+        assert_eq!(config.providers[0].location_id, None);
+        assert_eq!(config.providers[1].kind, AccuWeather);
+        assert_eq!(config.providers[1].location_id.as_deref(), Some("324505"));
+    }
+
+    #[test]
+    fn migrate_layout_with_settings() {
+        let (config, version) = parse_fixture("v1-settings.toml");
+        assert_eq!(version, 1);
+        assert_eq!(config.language, Some(Language::Uk));
+        assert_eq!(config.fallback_providers, vec![OpenWeather]);
+        assert_eq!(config.providers[0].minute_limit, Some(30));
+        assert_eq!(config.providers[1].kind, WeatherApi);
+        assert_eq!(config.http.timeout, 20);
+        assert_eq!(config.http.proxy.as_deref(), Some("http://proxy:8080"));
+
+        // The only saved location is taken, even though its provider is not active.
+        let location = config.location.unwrap();
+        assert_eq!(location.name, "Ternopil");
+        assert_eq!(location.lat, Some(49.5557716));
+    }
+
+    #[test]
+    fn migrate_layout_with_shared_location() {
+        let (config, version) = parse_fixture("v1-shared-location.toml");
+        assert_eq!(version, 1);
+        assert_eq!(config.location.unwrap().name, "Kyiv");
+        assert_eq!(config.providers[0].location_id.as_deref(), Some("324505"));
+    }
+
+    #[test]
+    fn keep_current_layout() {
+        let (config, version) = parse_fixture("v2.toml");
+        assert_eq!(version, CURRENT_VERSION);
+        assert_eq!(config.fallback_providers, vec![AccuWeather]);
+        assert_eq!(config.providers[1].daily_limit, Some(0));
+        assert_eq!(config.providers[1].location_id.as_deref(), Some("324505"));
+        assert_eq!(config.location.unwrap().lat, Some(50.45));
+    }
+
+    #[test]
+    fn refuse_unknown_versions() {
+        for version in [0, CURRENT_VERSION as i64 + 1] {
+            let mut config = toml::from_str(&format!("version = {version}")).unwrap();
+            assert!(matches!(
+                migrate(&mut config),
+                Err(Error::UnsupportedConfigVersion { version: v, .. }) if v == version
+            ));
+        }
+    }
+}
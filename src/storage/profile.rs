@@ -0,0 +1,235 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use confy::ConfyError;
+use log::{debug, warn};
+
+use crate::error::{Error, Result};
+
+use super::{APP_NAME, DEFAULT_CONFIG_NAME};
+
+/// Profile that uses the config from the standard place.
+pub const DEFAULT_PROFILE: &str = "default";
+
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Set profile for all further config operations without an explicit path, can be done only once.
+pub fn set_profile(name: &str) -> Result<()> {
+    check_name(name)?;
+    if PROFILE.set(name.to_owned()).is_err() {
+        warn!("profile is already set");
+    }
+    Ok(())
+}
+
+pub fn profile() -> &'static str {
+    PROFILE.get().map_or(DEFAULT_PROFILE, String::as_str)
+}
+
+/// Named configs kept next to the default one.
+pub struct Profiles {
+    dir: PathBuf,
+}
+
+impl Profiles {
+    pub fn new() -> Result<Self> {
+        let path = confy::get_configuration_file_path(APP_NAME, DEFAULT_CONFIG_NAME)?;
+        let dir = path
+            .parent()
+            .expect("config file should have a parent directory")
+            .to_owned();
+        Ok(Self { dir })
+    }
+
+    /// Path to the config of the profile, which may not exist yet.
+    pub fn path(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_PROFILE {
+            self.dir.join(format!("{DEFAULT_CONFIG_NAME}.toml"))
+        } else {
+            self.dir.join("profiles").join(format!("{name}.toml"))
+        }
+    }
+
+    /// Names of existing profiles, the default one goes first.
+    pub fn list(&self) -> Result<Vec<String>> {
+        let entries = match fs::read_dir(self.dir.join("profiles")) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![DEFAULT_PROFILE.into()]),
+            Err(e) => return Err(ConfyError::GeneralLoadError(e).into()),
+        };
+        let mut names = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(ConfyError::GeneralLoadError)?.path();
+            // Skip quota usages and backups kept next to configs.
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str()?.strip_suffix(".toml"))
+                .filter(|&name| check_name(name).is_ok());
+            if let Some(name) = name {
+                names.push(name.to_owned());
+            }
+        }
+        names.sort();
+        names.insert(0, DEFAULT_PROFILE.into());
+        Ok(names)
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.path(name).exists()
+    }
+
+    /// Fail if the profile has not been created, so a mistyped name does not silently start
+    /// with an empty config. The default profile always exists.
+    pub fn require(&self, name: &str) -> Result<()> {
+        if name != DEFAULT_PROFILE && !self.exists(name) {
+            return Err(Error::ProfileNotFound(name.to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Create the profile with the default config.
+    pub fn create(&self, name: &str) -> Result<()> {
+        self.check_new(name)?;
+        confy::load_path::<super::Config>(self.path(name))?;
+        debug!("created \"{name}\" profile");
+        Ok(())
+    }
+
+    /// Create the profile with the config of another one.
+    pub fn copy(&self, from: &str, to: &str) -> Result<()> {
+        check_name(from)?;
+        if !self.exists(from) {
+            return Err(Error::ProfileNotFound(from.to_owned()));
+        }
+        self.check_new(to)?;
+        let path = self.path(to);
+        let write_error = ConfyError::WriteConfigurationFileError;
+        fs::create_dir_all(self.dir.join("profiles")).map_err(write_error)?;
+        fs::copy(self.path(from), path).map_err(write_error)?;
+        debug!("copied \"{from}\" profile to \"{to}\"");
+        Ok(())
+    }
+
+    /// Delete config of the profile along with the files kept next to it, like quota usage,
+    /// alert states, the daemon state, locks and backups, so a new profile of the name starts clean.
+    pub fn delete(&self, name: &str) -> Result<()> {
+        check_name(name)?;
+        if !self.exists(name) {
+            return Err(Error::ProfileNotFound(name.to_owned()));
+        }
+        let path = self.path(name);
+        fs::remove_file(&path).map_err(ConfyError::WriteConfigurationFileError)?;
+        // Names have no dots, so every file of the profile starts with the name and a dot.
+        let prefix = format!("{name}.");
+        let entries =
+            fs::read_dir(self.dir.join("profiles")).map_err(ConfyError::GeneralLoadError)?;
+        for entry in entries.flatten() {
+            let belongs = entry
+                .file_name()
+                .to_str()
+                .is_some_and(|file| file.starts_with(&prefix));
+            if belongs {
+                if let Err(e) = fs::remove_file(entry.path()) {
+                    warn!(
+                        "failed to remove {:?} of \"{name}\" profile: {e}",
+                        entry.path()
+                    );
+                }
+            }
+        }
+        debug!("deleted \"{name}\" profile");
+        Ok(())
+    }
+
+    fn check_new(&self, name: &str) -> Result<()> {
+        check_name(name)?;
+        if self.exists(name) {
+            return Err(Error::ProfileExists(name.to_owned()));
+        }
+        Ok(())
+    }
+}
+
+/// Names become file names, so only safe characters are allowed.
+fn check_name(name: &str) -> Result<()> {
+    let is_valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if is_valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidProfileName(name.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn manage_profiles() {
+        let dir = TempDir::new().unwrap();
+        let profiles = Profiles {
+            dir: dir.to_path_buf(),
+        };
+        assert_eq!(profiles.list().unwrap(), vec![DEFAULT_PROFILE]);
+
+        profiles.create("work").unwrap();
+        assert!(profiles.path("work").exists());
+        assert!(matches!(
+            profiles.create("work"),
+            Err(Error::ProfileExists(_))
+        ));
+
+        profiles.copy("work", "demo-1").unwrap();
+        assert!(matches!(
+            profiles.copy("home", "other"),
+            Err(Error::ProfileNotFound(_))
+        ));
+        // Quota usage is not taken for a profile.
+        fs::write(profiles.path("work").with_extension("quota.toml"), "").unwrap();
+        assert_eq!(
+            profiles.list().unwrap(),
+            vec![DEFAULT_PROFILE, "demo-1", "work"]
+        );
+
+        profiles.delete("work").unwrap();
+        assert!(!profiles.path("work").with_extension("quota.toml").exists());
+
+        // A recreated profile does not inherit states, locks and backups of the deleted one.
+        profiles.create("work").unwrap();
+        let path = profiles.path("work");
+        for extension in ["alerts.toml", "daemon.json", "lock", "v1.bak"] {
+            fs::write(path.with_extension(extension), "").unwrap();
+        }
+        profiles.delete("work").unwrap();
+        profiles.create("work").unwrap();
+        let mut files: Vec<_> = fs::read_dir(dir.join("profiles"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["demo-1.toml", "work.toml"]);
+        profiles.delete("work").unwrap();
+        assert!(matches!(
+            profiles.delete("work"),
+            Err(Error::ProfileNotFound(_))
+        ));
+        assert_eq!(profiles.list().unwrap(), vec![DEFAULT_PROFILE, "demo-1"]);
+    }
+
+    #[test]
+    fn refuse_unsafe_names() {
+        for name in ["", "../work", "work.toml", "my work"] {
+            assert!(matches!(
+                check_name(name),
+                Err(Error::InvalidProfileName(_))
+            ));
+        }
+    }
+}
@@ -6,6 +6,14 @@ use dialoguer::theme::ColorfulTheme;
 use indicatif::ProgressBar;
 use proc_exit::Code;
 
+pub use art::art;
+pub use icons::IconSet;
+
+use weather::data::{Condition, Weather};
+
+mod art;
+mod icons;
+
 pub fn theme() -> &'static ColorfulTheme {
     static THEME: OnceLock<ColorfulTheme> = OnceLock::<ColorfulTheme>::new();
     THEME.get_or_init(ColorfulTheme::default)
@@ -25,26 +33,35 @@ pub fn sprintln(msg: &str) {
     );
 }
 
-// Error style println and exit.
-pub fn eprintln(msg: &str) -> ! {
+// Error style println without exiting.
+pub fn wprintln(msg: &str) {
     eprintln!(
         "{} {}",
         theme().error_prefix,
         theme().error_style.apply_to(msg)
     );
-    Code::FAILURE.process_exit()
 }
 
-pub fn get_style_for_weather(description: &str) -> Style {
+// Error style println and exit with the given code.
+pub fn eprintln(msg: &str, code: Code) -> ! {
+    wprintln(msg);
+    code.process_exit()
+}
+
+pub fn get_style_for_weather(weather: &Weather) -> Style {
     let style = Style::new().for_stderr().bold();
-    match description.to_lowercase() {
-        s if s.contains("clear") => style.color256(4),
-        s if s.contains("cloud") => style.color256(7),
-        s if s.contains("sun") => style.color256(11),
-        s if s.contains("rain") => style.color256(12),
-        s if s.contains("wind") => style.color256(14),
-        s if s.contains("snow") => style.color256(15),
-        _ => style,
+    match weather.condition {
+        Condition::Clear if weather.is_day => style.color256(11),
+        Condition::Clear => style.color256(4),
+        Condition::PartlyCloudy | Condition::Cloudy | Condition::Overcast => style.color256(7),
+        Condition::Mist | Condition::Fog | Condition::Haze => style.color256(8),
+        Condition::Drizzle | Condition::Rain | Condition::FreezingRain => style.color256(12),
+        Condition::HeavyRain | Condition::Thunderstorm => style.color256(13),
+        Condition::Snow | Condition::HeavySnow | Condition::Sleet | Condition::Hail => {
+            style.color256(15)
+        }
+        Condition::Windy => style.color256(14),
+        Condition::Unknown => style,
     }
 }
 
@@ -0,0 +1,171 @@
+use weather::data::Condition;
+
+type Art = [&'static str; 5];
+
+const SUN: Art = [
+    r"    \   /     ",
+    r"     .-.      ",
+    r"  - (   ) -   ",
+    r"     `-'      ",
+    r"    /   \     ",
+];
+
+const MOON: Art = [
+    r"     .--.     ",
+    r"    /  .-'    ",
+    r"   |  (       ",
+    r"    \  '-.    ",
+    r"     '--'     ",
+];
+
+const SUN_BEHIND_CLOUD: Art = [
+    r"   \  /       ",
+    r#" _ /"".-.     "#,
+    r"   \_(   ).   ",
+    r"   /(___(__)  ",
+    r"              ",
+];
+
+const MOON_BEHIND_CLOUD: Art = [
+    r"    .-.       ",
+    r"   (  .-.     ",
+    r"    '(   ).   ",
+    r"    (___(__)  ",
+    r"              ",
+];
+
+const CLOUD: Art = [
+    r"              ",
+    r"     .--.     ",
+    r"  .-(    ).   ",
+    r" (___.__)__)  ",
+    r"              ",
+];
+
+const CLOUDS: Art = [
+    r"     .--.     ",
+    r"  .-(    ).   ",
+    r" (___.__)__)  ",
+    r"  .-(    ).   ",
+    r" (___.__)__)  ",
+];
+
+const FOG: Art = [
+    r"              ",
+    r" _ - _ - _ -  ",
+    r"  _ - _ - _   ",
+    r" _ - _ - _ -  ",
+    r"              ",
+];
+
+const DRIZZLE: Art = [
+    r"     .-.      ",
+    r"    (   ).    ",
+    r"   (___(__)   ",
+    r"    ' ' ' '   ",
+    r"   ' ' ' '    ",
+];
+
+const RAIN: Art = [
+    r"     .-.      ",
+    r"    (   ).    ",
+    r"   (___(__)   ",
+    r"    / / / /   ",
+    r"   / / / /    ",
+];
+
+const HEAVY_RAIN: Art = [
+    r"     .-.      ",
+    r"    (   ).    ",
+    r"   (___(__)   ",
+    r"   ////////   ",
+    r"  ////////    ",
+];
+
+const FREEZING_RAIN: Art = [
+    r"     .-.      ",
+    r"    (   ).    ",
+    r"   (___(__)   ",
+    r"    / * / *   ",
+    r"   * / * /    ",
+];
+
+const THUNDERSTORM: Art = [
+    r"     .-.      ",
+    r"    (   ).    ",
+    r"   (___(__)   ",
+    r"    /_ /_     ",
+    r"     /  /     ",
+];
+
+const SNOW: Art = [
+    r"     .-.      ",
+    r"    (   ).    ",
+    r"   (___(__)   ",
+    r"    *  *  *   ",
+    r"   *  *  *    ",
+];
+
+const HEAVY_SNOW: Art = [
+    r"     .-.      ",
+    r"    (   ).    ",
+    r"   (___(__)   ",
+    r"   * * * * *  ",
+    r"  * * * * *   ",
+];
+
+const SLEET: Art = [
+    r"     .-.      ",
+    r"    (   ).    ",
+    r"   (___(__)   ",
+    r"    * / * /   ",
+    r"   / * / *    ",
+];
+
+const HAIL: Art = [
+    r"     .-.      ",
+    r"    (   ).    ",
+    r"   (___(__)   ",
+    r"    o  o  o   ",
+    r"   o  o  o    ",
+];
+
+const WIND: Art = [
+    r"              ",
+    r"   ~~~~~~     ",
+    r"  ~~~~~~~~    ",
+    r"   ~~~~~~     ",
+    r"              ",
+];
+
+const UNKNOWN: Art = [
+    r"    .-.       ",
+    r"     __)      ",
+    r"    (         ",
+    r"     `-'      ",
+    r"      .       ",
+];
+
+/// Multi-line ASCII art for the given condition.
+pub fn art(condition: Condition, is_day: bool) -> &'static Art {
+    match condition {
+        Condition::Clear if is_day => &SUN,
+        Condition::Clear => &MOON,
+        Condition::PartlyCloudy if is_day => &SUN_BEHIND_CLOUD,
+        Condition::PartlyCloudy => &MOON_BEHIND_CLOUD,
+        Condition::Cloudy => &CLOUD,
+        Condition::Overcast => &CLOUDS,
+        Condition::Mist | Condition::Fog | Condition::Haze => &FOG,
+        Condition::Drizzle => &DRIZZLE,
+        Condition::Rain => &RAIN,
+        Condition::HeavyRain => &HEAVY_RAIN,
+        Condition::FreezingRain => &FREEZING_RAIN,
+        Condition::Thunderstorm => &THUNDERSTORM,
+        Condition::Snow => &SNOW,
+        Condition::HeavySnow => &HEAVY_SNOW,
+        Condition::Sleet => &SLEET,
+        Condition::Hail => &HAIL,
+        Condition::Windy => &WIND,
+        Condition::Unknown => &UNKNOWN,
+    }
+}
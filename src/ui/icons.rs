@@ -0,0 +1,108 @@
+use clap::ValueEnum;
+
+use crate::i18n::system_locale;
+use weather::data::Condition;
+
+/// Set of glyphs used to show weather conditions.
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+pub enum IconSet {
+    Emoji,
+    Nerd,
+    Ascii,
+    None,
+}
+
+impl IconSet {
+    /// Fall back to ASCII icons if the terminal cannot render Unicode glyphs.
+    pub fn resolve(self) -> Self {
+        match self {
+            Self::Emoji | Self::Nerd if !is_utf8_locale() => Self::Ascii,
+            icons => icons,
+        }
+    }
+
+    pub fn icon(self, condition: Condition, is_day: bool) -> Option<&'static str> {
+        match self {
+            Self::Emoji => Some(emoji(condition, is_day)),
+            Self::Nerd => Some(nerd(condition, is_day)),
+            Self::Ascii => Some(ascii(condition, is_day)),
+            Self::None => None,
+        }
+    }
+}
+
+fn is_utf8_locale() -> bool {
+    if cfg!(windows) {
+        return true;
+    }
+    system_locale("LC_CTYPE")
+        .map(|value| {
+            let value = value.to_lowercase();
+            value.contains("utf-8") || value.contains("utf8")
+        })
+        .unwrap_or(false)
+}
+
+fn emoji(condition: Condition, is_day: bool) -> &'static str {
+    match condition {
+        Condition::Clear if is_day => "\u{2600}\u{fe0f}",
+        Condition::Clear => "\u{1f319}",
+        Condition::PartlyCloudy if is_day => "\u{26c5}",
+        Condition::PartlyCloudy | Condition::Cloudy | Condition::Overcast => "\u{2601}\u{fe0f}",
+        Condition::Mist | Condition::Fog | Condition::Haze => "\u{1f32b}\u{fe0f}",
+        Condition::Drizzle => "\u{1f326}\u{fe0f}",
+        Condition::Rain | Condition::HeavyRain | Condition::FreezingRain => "\u{1f327}\u{fe0f}",
+        Condition::Thunderstorm => "\u{26c8}\u{fe0f}",
+        Condition::Snow | Condition::Sleet => "\u{1f328}\u{fe0f}",
+        Condition::HeavySnow => "\u{2744}\u{fe0f}",
+        Condition::Hail => "\u{1f9ca}",
+        Condition::Windy => "\u{1f4a8}",
+        Condition::Unknown => "\u{2754}",
+    }
+}
+
+// Glyphs from the Weather Icons set patched into Nerd Fonts.
+fn nerd(condition: Condition, is_day: bool) -> &'static str {
+    match condition {
+        Condition::Clear if is_day => "\u{e30d}",
+        Condition::Clear => "\u{e32b}",
+        Condition::PartlyCloudy if is_day => "\u{e302}",
+        Condition::PartlyCloudy => "\u{e37e}",
+        Condition::Cloudy => "\u{e33d}",
+        Condition::Overcast => "\u{e312}",
+        Condition::Mist | Condition::Fog => "\u{e313}",
+        Condition::Haze => "\u{e36b}",
+        Condition::Drizzle => "\u{e31c}",
+        Condition::Rain | Condition::HeavyRain => "\u{e318}",
+        Condition::FreezingRain => "\u{e316}",
+        Condition::Thunderstorm => "\u{e31d}",
+        Condition::Snow | Condition::HeavySnow => "\u{e31a}",
+        Condition::Sleet => "\u{e3ad}",
+        Condition::Hail => "\u{e314}",
+        Condition::Windy => "\u{e34b}",
+        Condition::Unknown => "\u{e374}",
+    }
+}
+
+fn ascii(condition: Condition, is_day: bool) -> &'static str {
+    match condition {
+        Condition::Clear if is_day => "*",
+        Condition::Clear => "C",
+        Condition::PartlyCloudy if is_day => "~*",
+        Condition::PartlyCloudy => "~C",
+        Condition::Cloudy => "~~",
+        Condition::Overcast => "==",
+        Condition::Mist | Condition::Fog | Condition::Haze => "--",
+        Condition::Drizzle => "..",
+        Condition::Rain => "//",
+        Condition::HeavyRain => "///",
+        Condition::FreezingRain => "/*",
+        Condition::Thunderstorm => "/!",
+        Condition::Snow => "**",
+        Condition::HeavySnow => "***",
+        Condition::Sleet => "*/",
+        Condition::Hail => "oo",
+        Condition::Windy => ">>",
+        Condition::Unknown => "?",
+    }
+}
@@ -1,8 +1,12 @@
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
 
 use anyhow::Result;
 use assert_cmd::prelude::*;
+use assert_fs::prelude::*;
 use assert_fs::NamedTempFile;
+use predicates::prelude::*;
 use predicates::str::contains;
 
 const BIN_NAME: &str = env!("CARGO_PKG_NAME");
@@ -17,6 +21,9 @@ fn help_flag() -> Result<()> {
         .stdout(contains("Simple weather CLI"))
         .stdout(contains("configure"))
         .stdout(contains("get"))
+        .stdout(contains("quota"))
+        .stdout(contains("config"))
+        .stdout(contains("provider"))
         .stdout(contains("help"))
         .stdout(contains("-h, --help"))
         .stdout(contains("-V, --version"));
@@ -43,7 +50,10 @@ fn configure_command_help_flag() -> Result<()> {
         .success()
         .stdout(contains("Configure credentials for the provider"))
         .stdout(contains("<PROVIDER>  Specific weather API provider [possible values: open-weather, weather-api, accu-weather]"))
-        .stdout(contains("-c, --config <CONFIG>  Path to config file"));
+        .stdout(contains("-c, --config <CONFIG>    Path to config file"))
+        .stdout(contains("--profile <PROFILE>  Named profile with its own config"))
+        .stdout(contains("Network options:"))
+        .stdout(contains("--timeout <TIMEOUT>                  Timeout for provider API requests in seconds"));
 
     Ok(())
 }
@@ -79,9 +89,20 @@ fn get_command_help_flag() -> Result<()> {
         .assert()
         .success()
         .stdout(contains("Show weather by location"))
-        .stdout(contains("[LOCATION]  Choose a location (city, town, or village) and save the choice per provider"))
+        .stdout(contains("[LOCATIONS]...  Choose a location (city, town, or village) and save the choice for all providers, several ones are shown at once without saving"))
         .stdout(contains("-p, --provider <PROVIDER>  Choose an active provider and save the choice [possible values: open-weather, weather-api, accu-weather]"))
-        .stdout(contains("-c, --config <CONFIG>      Path to config file"));
+        .stdout(contains("-l, --lang <LANG>          Choose a language of output and save the choice [possible values: en, uk]"))
+        .stdout(contains("--icons <ICONS>        Prefix weather conditions with icons [default: none] [possible values: emoji, nerd, ascii, none]"))
+        .stdout(contains("--art                  Show weather conditions as ASCII art"))
+        .stdout(contains("-c, --config <CONFIG>      Path to config file"))
+        .stdout(contains("Network options:"))
+        .stdout(contains("--timeout <TIMEOUT>                  Timeout for provider API requests in seconds"))
+        .stdout(contains("--read-timeout <READ_TIMEOUT>        Timeout for every read of the response in seconds"))
+        .stdout(contains("--proxy <PROXY>                      Proxy for provider API requests, can contain credentials"))
+        .stdout(contains("--no-proxy <NO_PROXY>                Comma-separated list of hosts that should not be proxied"))
+        .stdout(contains("--ca-cert <CA_CERT>                  PEM file with extra root certificates to trust"))
+        .stdout(contains("--min-tls-version <MIN_TLS_VERSION>  Minimum TLS version [possible values: 1.0, 1.1, 1.2, 1.3]"))
+        .stdout(contains("--parallel <PARALLEL>                Maximum number of concurrent requests to provider APIs"));
 
     Ok(())
 }
@@ -107,26 +128,761 @@ fn get_command_without_configured_provider() -> Result<()> {
         .arg("get")
         .args(["-c", config.to_str().unwrap()])
         .assert()
-        .failure()
+        .code(64)
         .stderr(contains("None of the providers is configured."));
 
     Command::cargo_bin(BIN_NAME)?
         .args(["get", "Kyiv"])
         .args(["-c", config.to_str().unwrap()])
         .assert()
-        .failure()
+        .code(64)
         .stderr(contains("None of the providers is configured."));
 
     Command::cargo_bin(BIN_NAME)?
         .args(["get", "Kyiv", "-popen-weather"])
         .args(["-c", config.to_str().unwrap()])
         .assert()
-        .failure()
+        .code(64)
         .stderr(contains("Provider is not configured."));
 
     Ok(())
 }
 
+#[test]
+fn quota_command_without_configured_provider() -> Result<()> {
+    let config = NamedTempFile::new("config").unwrap();
+
+    Command::cargo_bin(BIN_NAME)?
+        .arg("quota")
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .code(64)
+        .stderr(contains("None of the providers is configured."));
+
+    Ok(())
+}
+
+#[test]
+fn config_command_path() -> Result<()> {
+    let config = NamedTempFile::new("config").unwrap();
+
+    Command::cargo_bin(BIN_NAME)?
+        .args(["config", "path"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", config.display()));
+
+    Ok(())
+}
+
+#[test]
+fn config_command_validate() -> Result<()> {
+    let config = NamedTempFile::new("config").unwrap();
+
+    config.write_str("active_provider = \"OpenWeather\"\nproviders = 5\n")?;
+    Command::cargo_bin(BIN_NAME)?
+        .args(["config", "validate"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .code(78)
+        .stderr(contains(
+            "expected a sequence for key `providers` at line 2 column 13",
+        ));
+
+    config.write_str("providers = []\n")?;
+    Command::cargo_bin(BIN_NAME)?
+        .args(["config", "validate"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("Config is valid."));
+
+    Ok(())
+}
+
+#[test]
+fn config_command_show_and_reset() -> Result<()> {
+    let config = NamedTempFile::new("config").unwrap();
+    config.write_str(
+        "active_provider = \"OpenWeather\"\n\n[[providers]]\nkind = \"OpenWeather\"\napi_key = \"secret\"\n",
+    )?;
+
+    Command::cargo_bin(BIN_NAME)?
+        .args(["config", "show"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("api_key = \"redacted\""));
+
+    Command::cargo_bin(BIN_NAME)?
+        .args(["config", "show", "--show-secrets"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("api_key = \"secret\""));
+
+    Command::cargo_bin(BIN_NAME)?
+        .args(["provider", "list", "--no-check"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("OpenWeather (active)"))
+        .stdout(contains("API key: not checked"));
+
+    Command::cargo_bin(BIN_NAME)?
+        .args(["config", "reset", "--yes"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("Config has been reset."));
+
+    Command::cargo_bin(BIN_NAME)?
+        .args(["provider", "list"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .code(64)
+        .stderr(contains("None of the providers is configured."));
+
+    Ok(())
+}
+
+#[test]
+fn unconfigure_command() -> Result<()> {
+    let config = NamedTempFile::new("config").unwrap();
+    config.write_str(
+        "active_provider = \"OpenWeather\"\n\n[[providers]]\nkind = \"OpenWeather\"\napi_key = \"secret\"\n\n[[providers]]\nkind = \"WeatherApi\"\napi_key = \"secret\"\n",
+    )?;
+
+    Command::cargo_bin(BIN_NAME)?
+        .args(["unconfigure", "accu-weather", "--yes"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .code(64)
+        .stderr(contains("Provider is not configured."));
+
+    Command::cargo_bin(BIN_NAME)?
+        .args(["unconfigure", "open-weather", "--yes"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("Successfully removed provider configuration."))
+        .stdout(contains("Active provider: WeatherApi"));
+
+    Command::cargo_bin(BIN_NAME)?
+        .args(["locations", "clear", "--yes"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("Saved locations have been cleared."));
+
+    Command::cargo_bin(BIN_NAME)?
+        .arg("get")
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .code(64)
+        .stderr(contains("No saved location for active provider."));
+
+    Ok(())
+}
+
+#[test]
+fn concurrent_processes() -> Result<()> {
+    let config = NamedTempFile::new("config.toml").unwrap();
+    let providers = ["open-weather", "weather-api", "accu-weather"];
+
+    for _ in 0..5 {
+        config.write_str(
+            "version = 2\nactive_provider = \"OpenWeather\"\nfallback_providers = [\"WeatherApi\"]\n\n[[providers]]\nkind = \"OpenWeather\"\napi_key = \"secret\"\n\n[[providers]]\nkind = \"WeatherApi\"\napi_key = \"secret\"\n\n[[providers]]\nkind = \"AccuWeather\"\napi_key = \"secret\"\n\n[location]\nname = \"Kyiv\"\ncountry = \"UA\"\nlat = 50.45\nlon = 30.52\n",
+        )?;
+
+        // Every process removes its own part, none of the removals should be lost.
+        let mut children = Vec::new();
+        for provider in providers {
+            children.push(
+                Command::cargo_bin(BIN_NAME)?
+                    .args(["unconfigure", provider, "--yes"])
+                    .args(["-c", config.to_str().unwrap()])
+                    .stdout(Stdio::null())
+                    .spawn()?,
+            );
+        }
+        for _ in 0..10 {
+            children.push(
+                Command::cargo_bin(BIN_NAME)?
+                    .args(["locations", "clear", "--yes"])
+                    .args(["-c", config.to_str().unwrap()])
+                    .stdout(Stdio::null())
+                    .spawn()?,
+            );
+        }
+        for mut child in children {
+            assert!(child.wait()?.success());
+        }
+
+        Command::cargo_bin(BIN_NAME)?
+            .args(["config", "validate"])
+            .args(["-c", config.to_str().unwrap()])
+            .assert()
+            .success();
+        Command::cargo_bin(BIN_NAME)?
+            .args(["config", "show"])
+            .args(["-c", config.to_str().unwrap()])
+            .assert()
+            .success()
+            .stdout(contains("providers = []"))
+            .stdout(contains("[location]").not());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn export_and_import_commands() -> Result<()> {
+    let config = NamedTempFile::new("config.toml").unwrap();
+    config.write_str(
+        "active_provider = \"OpenWeather\"\n\n[[providers]]\nkind = \"OpenWeather\"\napi_key = \"secret\"\n",
+    )?;
+    let locations = NamedTempFile::new("sites.csv").unwrap();
+    locations.write_str("name,lat,lon,country\nOffice,49.84,24.03,UA\nDepot,50.0,30.0,UA\n")?;
+
+    Command::cargo_bin(BIN_NAME)?
+        .args(["import", locations.to_str().unwrap()])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("Imported values: 2"));
+    Command::cargo_bin(BIN_NAME)?
+        .args(["locations", "list"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("Office, UA"))
+        .stdout(contains("Depot, UA"));
+
+    let export = NamedTempFile::new("export.toml").unwrap();
+    Command::cargo_bin(BIN_NAME)?
+        .args(["export", export.to_str().unwrap()])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .success();
+    export.assert(predicate::str::contains("Office").and(contains("secret").not()));
+
+    // Import into another config reports what could not be taken.
+    let other = NamedTempFile::new("other.toml").unwrap();
+    other.write_str(
+        "providers = []\n\n[[locations]]\nname = \"Depot\"\ncountry = \"UA\"\nlat = 46.48\nlon = 30.72\n",
+    )?;
+    Command::cargo_bin(BIN_NAME)?
+        .args(["import", export.to_str().unwrap()])
+        .args(["-c", other.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("Imported values: 1"))
+        .stdout(contains("Use --overwrite to replace current values."))
+        .stderr(contains("Skipped provider without API key: OpenWeather"))
+        .stderr(contains(
+            "Kept current value on conflict: location \"Depot\"",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn get_command_several_locations() -> Result<()> {
+    let config = NamedTempFile::new("config.toml").unwrap();
+    config.write_str(concat!(
+        "active_provider = \"OpenWeather\"\n\n",
+        "[[providers]]\nkind = \"OpenWeather\"\napi_key = \"secret\"\n\n",
+        "[[locations]]\nname = \"Office\"\ncountry = \"UA\"\nlat = 49.84\nlon = 24.03\n\n",
+        "[[locations]]\nname = \"Depot\"\ncountry = \"UA\"\nlat = 50.0\nlon = 30.0\n\n",
+        "[http]\nretries = 0\n",
+    ))?;
+
+    // Every location is shown in order, even if the provider cannot be reached.
+    Command::cargo_bin(BIN_NAME)?
+        .args(["get", "Office", "Depot", "--parallel", "2"])
+        .args(["--proxy", "http://127.0.0.1:1"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .code(74)
+        .stdout(contains("Chosen location: Office, UA").and(contains("Chosen location: Depot, UA")))
+        .stderr(contains("Failed to get weather for some locations."));
+
+    // Several locations are not saved.
+    config.assert(predicate::str::contains("[location]").not());
+
+    Ok(())
+}
+
+#[test]
+fn astro_command() -> Result<()> {
+    let config = NamedTempFile::new("config.toml").unwrap();
+    config.write_str(concat!(
+        "providers = []\n\n",
+        "[[locations]]\nname = \"Office\"\ncountry = \"UA\"\nlat = 50.45\nlon = 30.52\n",
+        "timezone = \"Europe/Kyiv\"\n\n",
+        "[[locations]]\nname = \"Depot\"\ncountry = \"UA\"\n",
+    ))?;
+
+    // Known locations and coordinates need no provider.
+    Command::cargo_bin(BIN_NAME)?
+        .args(["astro", "Office", "--date", "2024-06-21"])
+        .args(["-c", config.to_str().unwrap()])
+        .env("TZ", "UTC")
+        .assert()
+        .success()
+        .stdout(
+            contains("Sunrise: 01:4")
+                .and(contains("Sunset: 18:1"))
+                .and(contains("Day length: 16 h 2"))
+                .and(contains("Moon: full moon"))
+                // The sun is never low enough for astronomical twilight.
+                .and(contains("Astronomical dawn").not()),
+        );
+
+    // Times can be shown in the time zone of the location, with daylight saving time.
+    Command::cargo_bin(BIN_NAME)?
+        .args(["astro", "Office", "--date", "2024-06-21", "--tz", "site"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("Time zone: +03:00").and(contains("Sunrise: 04:4")));
+
+    // Time zone of coordinates is unknown without asking providers.
+    Command::cargo_bin(BIN_NAME)?
+        .args([
+            "astro",
+            "69.65,18.96",
+            "--date",
+            "2024-12-21",
+            "--tz",
+            "site",
+        ])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(contains("Polar night").and(contains("Sunrise").not()))
+        .stderr(contains("Time zone of the location is unknown"));
+
+    Command::cargo_bin(BIN_NAME)?
+        .args(["astro", "Depot"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .code(65)
+        .stderr(contains("Location has no coordinates."));
+
+    Ok(())
+}
+
+#[test]
+fn check_command() -> Result<()> {
+    let config = NamedTempFile::new("config.toml").unwrap();
+    let providers = concat!(
+        "active_provider = \"OpenWeather\"\n\n",
+        "[[providers]]\nkind = \"OpenWeather\"\napi_key = \"secret\"\n\n",
+        "[[locations]]\nname = \"Office\"\ncountry = \"UA\"\nlat = 49.84\nlon = 24.03\n\n",
+    );
+    config.write_str(providers)?;
+    Command::cargo_bin(BIN_NAME)?
+        .args(["check", "-c", config.to_str().unwrap()])
+        .assert()
+        .code(64)
+        .stderr(contains("No alerts are defined in the config."));
+
+    config.write_str(&format!(
+        "{providers}{}",
+        concat!(
+            "[[alerts]]\nname = \"freeze\"\nrule = \"temp < -5 at @office\"\n\n",
+            "[[alerts]]\nname = \"storm\"\nrule = \"wind > 15 m/s at @depot\"\n\n",
+            "[http]\nretries = 0\n",
+        )
+    ))?;
+    // Every alert is checked, the exit code is of the first failure.
+    Command::cargo_bin(BIN_NAME)?
+        .args(["check", "--proxy", "http://127.0.0.1:1"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .code(74)
+        .stderr(
+            contains("freeze: Failed to communicate with provider API")
+                .and(contains("storm: Known location is not found: \"depot\""))
+                .and(contains("Failed to check some alerts.")),
+        );
+
+    config.write_str(&format!(
+        "{providers}[[alerts]]\nname = \"freeze\"\nrule = \"temp < cold\"\n"
+    ))?;
+    Command::cargo_bin(BIN_NAME)?
+        .args(["check", "-c", config.to_str().unwrap()])
+        .assert()
+        .code(78)
+        .stderr(contains("rule \"temp < cold\" is invalid"));
+
+    Ok(())
+}
+
+#[test]
+fn get_command_batch() -> Result<()> {
+    let config = NamedTempFile::new("config.toml").unwrap();
+    config.write_str(concat!(
+        "active_provider = \"OpenWeather\"\n\n",
+        "[[providers]]\nkind = \"OpenWeather\"\napi_key = \"secret\"\n\n",
+        "[[locations]]\nname = \"Office\"\ncountry = \"UA\"\nlat = 49.84\nlon = 24.03\n\n",
+        "[http]\nretries = 0\n",
+    ))?;
+    let sites = NamedTempFile::new("sites.txt").unwrap();
+    sites.write_str("# monitored sites\nOffice\n50.45,30.52\n")?;
+
+    // Every site gets a row with its own error.
+    Command::cargo_bin(BIN_NAME)?
+        .args([
+            "get",
+            "--batch",
+            sites.to_str().unwrap(),
+            "--format",
+            "json",
+        ])
+        .args(["--proxy", "http://127.0.0.1:1"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .code(74)
+        .stdout(contains("\"site\": \"Office\""))
+        .stdout(contains("\"location\": \"Office, UA\""))
+        .stdout(contains("\"site\": \"50.45,30.52\""))
+        .stdout(contains(
+            "\"error\": \"Failed to communicate with provider API\"",
+        ))
+        .stderr(contains("Failed to get weather for some locations."));
+
+    Command::cargo_bin(BIN_NAME)?
+        .args(["get", "--batch", sites.to_str().unwrap(), "--format", "csv"])
+        .args(["--proxy", "http://127.0.0.1:1"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .code(74)
+        .stdout(contains(
+            "site,location,lat,lon,provider,temperature,condition,description,ambiguous,error\n",
+        ))
+        .stdout(contains(
+            "Office,\"Office, UA\",49.84,24.03,,,,,false,Failed to communicate with provider API\n",
+        ));
+
+    Command::cargo_bin(BIN_NAME)?
+        .args(["get", "Kyiv", "--batch", sites.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(contains("cannot be used with"));
+
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+#[test]
+fn serve_command() -> Result<()> {
+    let config = NamedTempFile::new("config.toml").unwrap();
+    config.write_str(concat!(
+        "active_provider = \"OpenWeather\"\n\n",
+        "[[providers]]\nkind = \"OpenWeather\"\napi_key = \"secret\"\n\n",
+        "[http]\nretries = 0\n",
+    ))?;
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+    let url = format!("http://127.0.0.1:{port}");
+
+    let mut server = Command::cargo_bin(BIN_NAME)?
+        .args(["serve", "--bind", &format!("127.0.0.1:{port}")])
+        .args(["--proxy", "http://127.0.0.1:1"])
+        .args(["-c", config.to_str().unwrap()])
+        .stdout(Stdio::null())
+        .spawn()?;
+    let health = (0..50).find_map(|_| {
+        thread::sleep(Duration::from_millis(100));
+        reqwest::blocking::get(format!("{url}/healthz")).ok()
+    });
+    let result = (|| -> Result<()> {
+        assert_eq!(health.expect("server should start").status(), 200);
+
+        for route in ["weather", "forecast"] {
+            let response =
+                reqwest::blocking::get(format!("{url}/v1/{route}?location=50.45,30.52"))?;
+            assert_eq!(response.status(), 502);
+            assert!(response
+                .text()?
+                .contains("Failed to communicate with provider API"));
+        }
+        Ok(())
+    })();
+    server.kill()?;
+    result
+}
+
+#[cfg(feature = "server")]
+#[test]
+fn exporter_command() -> Result<()> {
+    let config = NamedTempFile::new("config.toml").unwrap();
+    config.write_str(concat!(
+        "active_provider = \"OpenWeather\"\n\n",
+        "[[providers]]\nkind = \"OpenWeather\"\napi_key = \"secret\"\n\n",
+        "[[locations]]\nname = \"Office\"\ncountry = \"UA\"\nlat = 49.84\nlon = 24.03\n\n",
+        "[http]\nretries = 0\n",
+    ))?;
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+    let url = format!("http://127.0.0.1:{port}/metrics");
+
+    let mut exporter = Command::cargo_bin(BIN_NAME)?
+        .args(["exporter", "--bind", &format!("127.0.0.1:{port}")])
+        .args(["--proxy", "http://127.0.0.1:1"])
+        .args(["-c", config.to_str().unwrap()])
+        .stdout(Stdio::null())
+        .spawn()?;
+    // The first poll starts right away and fails quickly through the unreachable proxy.
+    let metrics = (0..50).find_map(|_| {
+        thread::sleep(Duration::from_millis(100));
+        reqwest::blocking::get(&url)
+            .and_then(|r| r.text())
+            .ok()
+            .filter(|text| text.contains("weather_fetch_errors_total{"))
+    });
+    exporter.kill()?;
+    let metrics = metrics.expect("exporter should poll and serve metrics");
+    assert!(
+        metrics.contains(r#"weather_fetch_errors_total{provider="OpenWeather",kind="network"} 1"#)
+    );
+    assert!(metrics.contains(r#"weather_fetch_duration_seconds_count{provider="OpenWeather"} 1"#));
+    assert!(!metrics.contains("weather_temperature_celsius{"));
+    Ok(())
+}
+
+// The daemon is stopped with a signal only on Unix.
+#[cfg(unix)]
+#[test]
+fn daemon_command() -> Result<()> {
+    let config = NamedTempFile::new("config.toml").unwrap();
+    config.write_str(concat!(
+        "active_provider = \"OpenWeather\"\n\n",
+        "[[providers]]\nkind = \"OpenWeather\"\napi_key = \"secret\"\n\n",
+        "[[locations]]\nname = \"Office\"\ncountry = \"UA\"\nlat = 49.84\nlon = 24.03\n\n",
+        "[http]\nretries = 0\n",
+    ))?;
+    let config_path = config.to_str().unwrap();
+    let state = config.path().with_extension("daemon.json");
+
+    let daemon = Command::cargo_bin(BIN_NAME)?
+        .args(["daemon", "run", "--proxy", "http://127.0.0.1:1"])
+        .args(["-c", config_path])
+        .env_remove("RUST_LOG")
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // The first fetch starts right away and fails quickly through the unreachable proxy.
+    let fetched = (0..50).any(|_| {
+        thread::sleep(Duration::from_millis(100));
+        std::fs::read_to_string(&state).is_ok_and(|s| s.contains("Failed to communicate"))
+    });
+    let result = (|| -> Result<()> {
+        assert!(fetched, "daemon should write its state");
+        Command::cargo_bin(BIN_NAME)?
+            .args(["daemon", "status", "-c", config_path])
+            .assert()
+            .success()
+            .stdout(
+                contains("Daemon is running, PID").and(contains("Office, UA: not fetched yet")),
+            );
+        Command::cargo_bin(BIN_NAME)?
+            .args(["daemon", "run", "-c", config_path])
+            .assert()
+            .code(1)
+            .stderr(contains("Daemon is already running, PID"));
+        Command::cargo_bin(BIN_NAME)?
+            .args(["daemon", "stop", "-c", config_path])
+            .assert()
+            .success()
+            .stdout(contains("Daemon has been stopped."));
+        Ok(())
+    })();
+    let output = daemon.wait_with_output()?;
+    result?;
+
+    // Logs are JSON lines with key-values.
+    let logs = String::from_utf8(output.stderr)?;
+    let failure = logs
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .find(|line| line["message"] == "failed to fetch weather")
+        .expect("failure should be logged");
+    assert_eq!(failure["level"], "WARN");
+    assert_eq!(failure["location"], "Office, UA");
+    assert!(!state.exists());
+
+    Command::cargo_bin(BIN_NAME)?
+        .args(["daemon", "status", "-c", config_path])
+        .assert()
+        .code(1)
+        .stderr(contains("Daemon is not running."));
+    Ok(())
+}
+
+#[test]
+fn daemon_cache() -> Result<()> {
+    let config = NamedTempFile::new("config.toml").unwrap();
+    config.write_str(concat!(
+        "active_provider = \"OpenWeather\"\n\n",
+        "[[providers]]\nkind = \"OpenWeather\"\napi_key = \"secret\"\n\n",
+        "[[locations]]\nname = \"Office\"\ncountry = \"UA\"\nlat = 49.84\nlon = 24.03\n\n",
+        "[[alerts]]\nname = \"freeze\"\nrule = \"temp < -5 at @office\"\n\n",
+        "[http]\nretries = 0\n",
+    ))?;
+    let state = config.path().with_extension("daemon.json");
+    let write_state = |next: u64| -> Result<()> {
+        let status = serde_json::json!({
+            "pid": 0,
+            "started": 0,
+            "locations": [{
+                "location": {"name": "Office", "country": "UA", "lat": 49.84, "lon": 24.03},
+                "fetched": 0,
+                "provider": "WeatherApi",
+                "weather": {
+                    "temperature": 263.15,
+                    "condition": "Snow",
+                    "is_day": true,
+                    "description": "Snow",
+                    "wind_speed": 5.0
+                },
+                "error": null,
+                "next": next
+            }]
+        });
+        Ok(std::fs::write(&state, status.to_string())?)
+    };
+
+    // Fresh weather of the daemon is used without asking providers.
+    write_state(u64::MAX)?;
+    Command::cargo_bin(BIN_NAME)?
+        .args(["get", "Office", "--proxy", "http://127.0.0.1:1"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(
+            contains("Snow, -10°C")
+                .and(contains("Answered by provider: WeatherApi"))
+                // Only the wind chill is known without humidity.
+                .and(contains("Feels like: -17°C (computed)"))
+                .and(contains("Wind chill: -17°C (computed)"))
+                .and(contains("Heat index").not()),
+        );
+    Command::cargo_bin(BIN_NAME)?
+        .args(["check", "--proxy", "http://127.0.0.1:1"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .code(3)
+        .stdout(contains("freeze (temp < -5 at @office): triggered"));
+
+    // Weather due for the next fetch is not used.
+    write_state(0)?;
+    Command::cargo_bin(BIN_NAME)?
+        .args(["get", "Office", "--proxy", "http://127.0.0.1:1"])
+        .args(["-c", config.to_str().unwrap()])
+        .assert()
+        .code(74);
+    Ok(())
+}
+
+// Config directory can be overridden only on Linux.
+#[cfg(target_os = "linux")]
+mod linux_tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn profile_command() -> Result<()> {
+        let home = TempDir::new()?;
+        let cmd = || -> Result<Command> {
+            let mut cmd = Command::cargo_bin(BIN_NAME)?;
+            cmd.env("XDG_CONFIG_HOME", home.path())
+                .env_remove("WEATHER_CLI_PROFILE");
+            Ok(cmd)
+        };
+
+        // Profiles can be managed while the env var points to one that does not exist yet.
+        cmd()?
+            .args(["profile", "list"])
+            .env("WEATHER_CLI_PROFILE", "work")
+            .assert()
+            .success()
+            .stdout("default\n");
+        cmd()?
+            .args(["profile", "create", "work"])
+            .env("WEATHER_CLI_PROFILE", "work")
+            .assert()
+            .success()
+            .stdout(contains("Successfully created profile."));
+        cmd()?
+            .args(["profile", "create", "work"])
+            .assert()
+            .code(64)
+            .stderr(contains("Profile \"work\" already exists"));
+        cmd()?
+            .args(["profile", "copy", "work", "demo"])
+            .assert()
+            .success();
+        cmd()?
+            .args(["profile", "list", "--profile", "work"])
+            .assert()
+            .success()
+            .stdout("default\ndemo\nwork (current)\n");
+
+        // Profile is chosen by flag or env var, explicit config path wins.
+        cmd()?
+            .args(["config", "path", "--profile", "work"])
+            .assert()
+            .success()
+            .stdout(contains("weather-cli/profiles/work.toml"));
+        // Only `profile create` makes a new profile.
+        cmd()?
+            .args(["config", "path", "--profile", "wrok"])
+            .assert()
+            .code(64)
+            .stderr(contains("Profile \"wrok\" does not exist"));
+        cmd()?
+            .args(["config", "path"])
+            .env("WEATHER_CLI_PROFILE", "demo")
+            .assert()
+            .success()
+            .stdout(contains("weather-cli/profiles/demo.toml"));
+        cmd()?
+            .args(["config", "path", "--profile", "work", "-c", "other.toml"])
+            .assert()
+            .success()
+            .stdout("other.toml\n");
+        cmd()?
+            .args(["config", "path", "--profile", "nope", "-c", "other.toml"])
+            .assert()
+            .success()
+            .stdout("other.toml\n");
+
+        cmd()?
+            .args(["profile", "delete", "default", "--yes"])
+            .assert()
+            .code(64);
+        cmd()?
+            .args(["profile", "delete", "work", "--yes"])
+            .assert()
+            .success()
+            .stdout(contains("Profile has been deleted."));
+        cmd()?
+            .args(["profile", "list"])
+            .assert()
+            .success()
+            .stdout("default (current)\ndemo\n");
+
+        Ok(())
+    }
+}
+
 #[cfg(not(target_os = "windows"))]
 mod not_windows_tests {
     // Currently, tests use env vars to get real API keys and make requests during tests.
@@ -338,7 +1094,7 @@ mod not_windows_tests {
         p.exp_eof()?;
 
         //
-        // Another provider can save its own location.
+        // Another provider uses the same saved location.
         //
 
         // Configure provider.
@@ -353,46 +1109,37 @@ mod not_windows_tests {
         p.exp_string("Successfully saved provider configuration.")?;
         p.exp_eof()?;
 
-        // Try to get weather for saved location.
-
-        Command::cargo_bin(BIN_NAME)?
-            .arg("get")
-            .args(["-c", config.to_str().unwrap()])
-            .assert()
-            .failure()
-            .stderr(contains("No saved location for active provider."));
-
-        // Get weather and save location.
+        // Get weather for saved location.
 
         let mut cmd = Command::cargo_bin(BIN_NAME)?;
-        cmd.args(["get", "London"])
-            .args(["-c", config.to_str().unwrap()]);
+        cmd.arg("get").args(["-c", config.to_str().unwrap()]);
 
         let mut p = spawn_command(cmd, TIMEOUT_MS)?;
-        p.exp_string("Several locations have been found, select one of them")?;
-        p.send_line(" ")?;
-        p.exp_string("London")?;
+        p.exp_string("Ternopil")?;
         p.exp_string("°C")?;
         p.exp_eof()?;
 
-        // Get weather for saved location.
+        // Get weather and save location.
 
         let mut cmd = Command::cargo_bin(BIN_NAME)?;
-        cmd.arg("get").args(["-c", config.to_str().unwrap()]);
+        cmd.args(["get", "London"])
+            .args(["-c", config.to_str().unwrap()]);
 
         let mut p = spawn_command(cmd, TIMEOUT_MS)?;
+        p.exp_string("Several locations have been found, select one of them")?;
+        p.send_line(" ")?;
         p.exp_string("London")?;
         p.exp_string("°C")?;
         p.exp_eof()?;
 
-        // Saved location of the first provider has not changed.
+        // New location is saved for the first provider too.
 
         let mut cmd = Command::cargo_bin(BIN_NAME)?;
         cmd.args(["get", "-p", first_provider.0])
             .args(["-c", config.to_str().unwrap()]);
 
         let mut p = spawn_command(cmd, TIMEOUT_MS)?;
-        p.exp_string("Ternopil")?;
+        p.exp_string("London")?;
         p.exp_string("°C")?;
         p.exp_eof()?;
 